@@ -0,0 +1,148 @@
+use crate::config::{get_config_path, Config};
+use crate::CacheCommand;
+use colored::Colorize;
+use jellyfin_rpc::{ImageCache, ImageHost, Imgur, Removed};
+use log::{debug, error, info};
+use std::time::Duration;
+use time::{macros::format_description, OffsetDateTime};
+
+/// Runs one of the `jellyfin-rpc cache` subcommands on the cache at `urls_path`.
+///
+/// The config is only read for the imgur client id, which is needed to delete images from imgur.
+pub fn run(
+    action: CacheCommand,
+    urls_path: &str,
+    config_path: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = match ImageCache::open(urls_path) {
+        Ok(cache) => cache,
+        Err(err) => {
+            error!(
+                "Image cache at {} could not be read: {}",
+                urls_path.red(),
+                err
+            );
+            std::process::exit(1)
+        }
+    };
+
+    match action {
+        CacheCommand::List => list(&cache),
+        CacheCommand::Stats => stats(&cache),
+        CacheCommand::Prune { older_than } => {
+            let imgur = imgur(config_path);
+            let older_than = older_than.map(|days| Duration::from_secs(days * 24 * 60 * 60));
+
+            report(cache.prune(older_than, &hosts(&imgur))?);
+        }
+        CacheCommand::Clear { host, forget } => {
+            let imgur = imgur(config_path);
+
+            report(cache.clear(host.as_deref(), &hosts(&imgur), forget)?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Imgur with the client id from the config, if there is one
+fn imgur(config_path: Option<String>) -> Option<Imgur> {
+    let config_path = config_path.or_else(|| get_config_path().ok())?;
+
+    let client_id = match Config::builder().load(&config_path) {
+        Ok(config) => config.build().imgur.client_id,
+        Err(err) => {
+            debug!(
+                "Config file could not be loaded at {}: {}",
+                config_path, err
+            );
+            None
+        }
+    };
+
+    if client_id.is_none() {
+        info!("No imgur client id, images uploaded to imgur are kept so they can be deleted later");
+    }
+
+    client_id.map(Imgur::new)
+}
+
+fn hosts(imgur: &Option<Imgur>) -> Vec<&dyn ImageHost> {
+    imgur.iter().map(|imgur| imgur as &dyn ImageHost).collect()
+}
+
+fn list(cache: &ImageCache) {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let images = cache.images();
+
+    for entry in &images {
+        let expiry = match entry.image.expires_at {
+            Some(expires_at) if expires_at <= now => "expired".red().to_string(),
+            Some(expires_at) => format!("expires {}", date(expires_at)),
+            None => String::from("never expires"),
+        };
+
+        println!(
+            "{:<10} {} {}",
+            entry.host.green(),
+            entry.image.url,
+            format!("(uploaded {}, {})", date(entry.image.uploaded_at), expiry).dimmed()
+        );
+    }
+
+    println!("{} images in {}", images.len(), cache.location().display());
+}
+
+fn stats(cache: &ImageCache) {
+    let stats = cache.stats();
+
+    for host in &stats {
+        println!(
+            "{:<10} {} images, {} expired, {} can be deleted, oldest uploaded {}",
+            host.host.green(),
+            host.images,
+            host.expired,
+            host.deletable,
+            host.oldest_upload
+                .map_or(String::from("at an unknown time"), date)
+        );
+    }
+
+    let size = std::fs::metadata(cache.location()).map_or(0, |metadata| metadata.len());
+
+    println!(
+        "{} images in {} ({} bytes)",
+        stats.iter().map(|host| host.images).sum::<usize>(),
+        cache.location().display(),
+        size
+    );
+}
+
+fn report(removed: Removed) {
+    println!(
+        "Removed {} images, {} of them were deleted from their host",
+        removed.removed, removed.deleted
+    );
+
+    if removed.failed > 0 {
+        println!(
+            "{} images couldn't be deleted from their host and were kept, \
+            `cache clear --forget` removes them anyway",
+            removed.failed.to_string().red()
+        );
+    }
+}
+
+fn date(timestamp: i64) -> String {
+    if timestamp == 0 {
+        return String::from("unknown");
+    }
+
+    OffsetDateTime::from_unix_timestamp(timestamp)
+        .ok()
+        .and_then(|date| {
+            date.format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                .ok()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
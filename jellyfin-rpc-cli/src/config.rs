@@ -1,6 +1,10 @@
-use jellyfin_rpc::{Button, DisplayFormat, MediaType, StatusType};
+use jellyfin_rpc::{
+    Button, DisplayFormat, ImageSource, MediaType, ServerConfig, ServerKind, SessionFilter,
+    SessionPriority, StatusType,
+};
 use log::debug;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::env;
 
 /// Main struct containing every other struct in the file.
@@ -25,18 +29,44 @@ pub struct Jellyfin {
     pub url: String,
     /// Api key from the jellyfin server, used to gather what's being watched.
     pub api_key: String,
+    /// Whether the server runs Jellyfin or Emby.
+    pub server_kind: ServerKind,
     /// Username of the person that info should be gathered from.
     pub username: Vec<String>,
+    /// User ids of the people that info should be gathered from, keeps working if they get renamed.
+    pub user_ids: Vec<String>,
+    /// Additional servers to look for sessions on.
+    pub servers: Vec<ServerConfig>,
+    /// Rules used to pick a session when several are active.
+    pub session_priority: Vec<SessionPriority>,
+    /// Mention music playing in another session while watching something.
+    pub combine_sessions: bool,
+    /// Only sessions matching this are displayed.
+    pub allow: SessionFilter,
+    /// Sessions matching this are never displayed.
+    pub deny: SessionFilter,
+    /// Only show sessions started on this machine.
+    pub local_only: bool,
+    /// Device ids that count as this machine.
+    pub local_device_ids: Vec<String>,
     /// Contains configuration for Music display.
     pub music: DisplayOptions,
     /// Contains configuration for Movie display.
     pub movies: DisplayOptions,
     /// Contains configuration for Episode display.
     pub episodes: DisplayOptions,
+    /// Contains configuration for Live TV display.
+    pub livetv: DisplayOptions,
+    /// Contains configuration for Book display.
+    pub books: DisplayOptions,
+    /// Contains configuration for Audiobook display.
+    pub audiobooks: DisplayOptions,
     /// Blacklist configuration.
     pub blacklist: Blacklist,
     /// Self signed certificate option
     pub self_signed_cert: bool,
+    /// Receive session updates over the websocket instead of polling
+    pub websocket: bool,
     /// Simple episode name
     pub show_simple: bool,
     /// Add "0" before season/episode number if lower than 10.
@@ -45,7 +75,7 @@ pub struct Jellyfin {
     pub add_divider: bool,
 }
 
-/// Contains configuration for Music/Movie display.
+/// Contains configuration for the display of a media type.
 pub struct DisplayOptions {
     /// Display is where you tell the program what should be displayed.
     pub display: Option<DisplayFormat>,
@@ -76,6 +106,8 @@ pub struct Images {
     pub litterbox_images: bool,
     /// Processes images by making them square and adding a blur.
     pub process_images: bool,
+    /// Images to try for each media type, e.g. `"episode": ["thumb", "season:primary"]`.
+    pub sources: HashMap<MediaType, Vec<ImageSource>>,
 }
 
 impl Config {
@@ -96,18 +128,46 @@ pub struct ConfigBuilder {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JellyfinBuilder {
     pub url: String,
+    /// Can be left out after running `jellyfin-rpc login`
+    #[serde(default)]
     pub api_key: String,
-    pub username: Username,
+    pub server_kind: Option<ServerKind>,
+    pub username: Option<Username>,
+    pub user_ids: Option<Vec<String>>,
+    pub servers: Option<Vec<ServerBuilder>>,
+    pub session_priority: Option<Vec<SessionPriority>>,
+    pub combine_sessions: Option<bool>,
+    pub allow: Option<SessionFilter>,
+    pub deny: Option<SessionFilter>,
+    pub local_only: Option<bool>,
+    pub local_device_ids: Option<Vec<String>>,
     pub music: Option<DisplayOptionsBuilder>,
     pub movies: Option<DisplayOptionsBuilder>,
     pub episodes: Option<DisplayOptionsBuilder>,
+    pub livetv: Option<DisplayOptionsBuilder>,
+    pub books: Option<DisplayOptionsBuilder>,
+    pub audiobooks: Option<DisplayOptionsBuilder>,
     pub blacklist: Option<Blacklist>,
     pub self_signed_cert: Option<bool>,
+    pub websocket: Option<bool>,
     pub show_simple: Option<bool>,
     pub append_prefix: Option<bool>,
     pub add_divider: Option<bool>,
 }
 
+/// An additional Jellyfin server, `url`, `api_key` and the users work like the main ones.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServerBuilder {
+    pub url: String,
+    pub api_key: String,
+    pub server_kind: Option<ServerKind>,
+    pub username: Option<Username>,
+    pub user_ids: Option<Vec<String>>,
+    pub self_signed_cert: Option<bool>,
+    /// Blacklisted on this server only, on top of `jellyfin.blacklist`
+    pub blacklist: Option<Blacklist>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Username {
@@ -164,9 +224,10 @@ pub struct ImagesBuilder {
     pub imgur_images: Option<bool>,
     pub litterbox_images: Option<bool>,
     pub process_images: Option<bool>,
+    pub sources: Option<HashMap<MediaType, Vec<ImageSource>>>,
 }
 
-/// Find urls.json in filesystem, used to store images that were already previously uploaded to imgur.
+/// Find urls.json in filesystem, used to store images that were already previously uploaded to an image host.
 ///
 /// This is to avoid the user having to specify a filepath on launch.
 ///
@@ -189,6 +250,27 @@ pub fn get_urls_path() -> Result<String, Box<dyn std::error::Error>> {
     }
 }
 
+/// Find login.json in filesystem, used to store the access token from `jellyfin-rpc login`.
+///
+/// Default login.json path depends on OS
+/// Windows: `%appdata%\jellyfin-rpc\login.json`
+/// Linux/macOS: `~/.config/jellyfin-rpc/login.json`
+pub fn get_login_path() -> Result<String, Box<dyn std::error::Error>> {
+    if cfg!(not(windows)) {
+        debug!("Platform is not Windows");
+        let xdg_config_home = match env::var("XDG_CONFIG_HOME") {
+            Ok(xdg_config_home) => xdg_config_home,
+            Err(_) => env::var("HOME")? + "/.config",
+        };
+
+        Ok(xdg_config_home + "/jellyfin-rpc/login.json")
+    } else {
+        debug!("Platform is Windows");
+        let app_data = env::var("APPDATA")?;
+        Ok(app_data + r"\jellyfin-rpc\login.json")
+    }
+}
+
 /// Find default config path (main.json) in filesystem.
 ///
 /// This is to avoid the user having to specify a filepath on launch.
@@ -218,13 +300,26 @@ impl ConfigBuilder {
         Self {
             jellyfin: JellyfinBuilder {
                 url: "".to_string(),
-                username: Username::String("".to_string()),
+                username: Some(Username::String("".to_string())),
+                user_ids: None,
+                servers: None,
+                session_priority: None,
+                combine_sessions: None,
+                allow: None,
+                deny: None,
+                local_only: None,
+                local_device_ids: None,
                 api_key: "".to_string(),
+                server_kind: None,
                 music: None,
                 movies: None,
                 episodes: None,
+                livetv: None,
+                books: None,
+                audiobooks: None,
                 blacklist: None,
                 self_signed_cert: None,
+                websocket: None,
                 show_simple: Some(false),
                 append_prefix: Some(false),
                 add_divider: Some(false),
@@ -248,88 +343,31 @@ impl ConfigBuilder {
     }
 
     pub fn build(self) -> Config {
-        let username = match self.jellyfin.username {
-            Username::Vec(usernames) => usernames,
-            Username::String(username) => username.split(',').map(|u| u.to_string()).collect(),
-        };
-
-        let music_display;
-        let music_separator;
-        let music_status_display_type;
-
-        if let Some(music) = self.jellyfin.music {
-            if let Some(disp) = music.display {
-                music_display = Some(match disp {
-                    Display::Vec(display) => DisplayFormat::from(display),
-                    Display::String(display) => DisplayFormat::from(display),
-                    Display::CustomFormat(display) => display,
+        let username = usernames(self.jellyfin.username);
+
+        let servers = self
+            .jellyfin
+            .servers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|server| {
+                let blacklist = server.blacklist.unwrap_or(Blacklist {
+                    media_types: None,
+                    libraries: None,
                 });
-            } else {
-                music_display = None;
-            }
-
-            music_separator = music.separator;
-
-            music_status_display_type = music
-                .status_display_type
-                .and_then(|x| StatusType::try_from(x).ok());
-        } else {
-            music_display = None;
-            music_separator = None;
-            music_status_display_type = None;
-        }
 
-        let movie_display;
-        let movie_separator;
-        let movie_status_display_type;
-
-        if let Some(movies) = self.jellyfin.movies {
-            if let Some(disp) = movies.display {
-                movie_display = Some(match disp {
-                    Display::Vec(display) => DisplayFormat::from(display),
-                    Display::String(display) => DisplayFormat::from(display),
-                    Display::CustomFormat(display) => display,
-                });
-            } else {
-                movie_display = None;
-            }
-
-            movie_separator = movies.separator;
-
-            movie_status_display_type = movies
-                .status_display_type
-                .and_then(|x| StatusType::try_from(x).ok());
-        } else {
-            movie_display = None;
-            movie_separator = None;
-            movie_status_display_type = None;
-        }
-
-        let episode_display;
-        let episode_separator;
-        let episode_status_display_type;
-
-        if let Some(episodes) = self.jellyfin.episodes {
-            if let Some(disp) = episodes.display {
-                episode_display = Some(match disp {
-                    Display::Vec(display) => DisplayFormat::from(display),
-                    Display::String(display) => DisplayFormat::from(display),
-                    Display::CustomFormat(display) => display,
-                });
-            } else {
-                episode_display = None;
-            }
-
-            episode_separator = episodes.separator;
-
-            episode_status_display_type = episodes
-                .status_display_type
-                .and_then(|x| StatusType::try_from(x).ok());
-        } else {
-            episode_display = None;
-            episode_separator = None;
-            episode_status_display_type = None;
-        }
+                ServerConfig {
+                    url: server.url,
+                    api_key: server.api_key,
+                    kind: server.server_kind.unwrap_or_default(),
+                    usernames: usernames(server.username),
+                    user_ids: server.user_ids.unwrap_or_default(),
+                    self_signed: server.self_signed_cert.unwrap_or(false),
+                    blacklist_media_types: blacklist.media_types.unwrap_or_default(),
+                    blacklist_libraries: blacklist.libraries.unwrap_or_default(),
+                }
+            })
+            .collect();
 
         let media_types;
         let libraries;
@@ -368,52 +406,48 @@ impl ConfigBuilder {
         let imgur_images;
         let litterbox_images;
         let process_images;
+        let image_sources;
 
         if let Some(images) = self.images {
             enable_images = images.enable_images.unwrap_or(false);
             imgur_images = images.imgur_images.unwrap_or(false);
             litterbox_images = images.litterbox_images.unwrap_or(false);
             process_images = images.process_images.unwrap_or(true);
+            image_sources = images.sources.unwrap_or_default();
         } else {
             enable_images = false;
             imgur_images = false;
             litterbox_images = false;
             process_images = true;
-        }
-
-        let url;
-
-        if self.jellyfin.url.ends_with("/") {
-            url = self.jellyfin.url;
-        } else {
-            url = self.jellyfin.url + "/"
+            image_sources = HashMap::new();
         }
 
         Config {
             jellyfin: Jellyfin {
-                url,
+                url: self.jellyfin.url,
                 api_key: self.jellyfin.api_key,
+                server_kind: self.jellyfin.server_kind.unwrap_or_default(),
                 username,
-                music: DisplayOptions {
-                    display: music_display,
-                    separator: music_separator,
-                    status_display_type: music_status_display_type,
-                },
-                movies: DisplayOptions {
-                    display: movie_display,
-                    separator: movie_separator,
-                    status_display_type: movie_status_display_type,
-                },
-                episodes: DisplayOptions {
-                    display: episode_display,
-                    separator: episode_separator,
-                    status_display_type: episode_status_display_type,
-                },
+                user_ids: self.jellyfin.user_ids.unwrap_or_default(),
+                servers,
+                session_priority: self.jellyfin.session_priority.unwrap_or_default(),
+                combine_sessions: self.jellyfin.combine_sessions.unwrap_or(false),
+                allow: self.jellyfin.allow.unwrap_or_default(),
+                deny: self.jellyfin.deny.unwrap_or_default(),
+                local_only: self.jellyfin.local_only.unwrap_or(false),
+                local_device_ids: self.jellyfin.local_device_ids.unwrap_or_default(),
+                music: display_options(self.jellyfin.music),
+                movies: display_options(self.jellyfin.movies),
+                episodes: display_options(self.jellyfin.episodes),
+                livetv: display_options(self.jellyfin.livetv),
+                books: display_options(self.jellyfin.books),
+                audiobooks: display_options(self.jellyfin.audiobooks),
                 blacklist: Blacklist {
                     media_types,
                     libraries,
                 },
                 self_signed_cert: self.jellyfin.self_signed_cert.unwrap_or(false),
+                websocket: self.jellyfin.websocket.unwrap_or(true),
                 show_simple: self.jellyfin.show_simple.unwrap_or(false),
                 append_prefix: self.jellyfin.append_prefix.unwrap_or(false),
                 add_divider: self.jellyfin.add_divider.unwrap_or(false),
@@ -429,7 +463,41 @@ impl ConfigBuilder {
                 imgur_images,
                 litterbox_images,
                 process_images,
+                sources: image_sources,
             },
         }
     }
 }
+
+/// Usernames can be a list or a comma separated string, empty ones are left out.
+fn usernames(username: Option<Username>) -> Vec<String> {
+    let usernames = match username {
+        Some(Username::Vec(usernames)) => usernames,
+        Some(Username::String(username)) => username.split(',').map(|u| u.to_string()).collect(),
+        None => Vec::new(),
+    };
+
+    usernames.into_iter().filter(|u| !u.is_empty()).collect()
+}
+
+fn display_options(options: Option<DisplayOptionsBuilder>) -> DisplayOptions {
+    let Some(options) = options else {
+        return DisplayOptions {
+            display: None,
+            separator: None,
+            status_display_type: None,
+        };
+    };
+
+    DisplayOptions {
+        display: options.display.map(|display| match display {
+            Display::Vec(display) => DisplayFormat::from(display),
+            Display::String(display) => DisplayFormat::from(display),
+            Display::CustomFormat(display) => display,
+        }),
+        separator: options.separator,
+        status_display_type: options
+            .status_display_type
+            .and_then(|x| StatusType::try_from(x).ok()),
+    }
+}
@@ -0,0 +1,155 @@
+use crate::config::Jellyfin;
+use colored::Colorize;
+use jellyfin_rpc::{Credentials, Device, Login};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long to wait for a Quick Connect code to be entered, Jellyfin forgets it after 10 minutes.
+const QUICK_CONNECT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Access token saved by `jellyfin-rpc login`, used when the config doesn't contain an api key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedLogin {
+    /// The server the token belongs to
+    pub url: String,
+    pub credentials: Credentials,
+}
+
+/// Loads the saved login, if there is one for `url`.
+pub fn load(path: &str, url: &str) -> Option<Credentials> {
+    let data = std::fs::read_to_string(path).ok()?;
+
+    let saved: SavedLogin = match serde_json::from_str(&data) {
+        Ok(saved) => saved,
+        Err(err) => {
+            warn!("Saved login at {} could not be read: {}", path, err);
+            return None;
+        }
+    };
+
+    if saved.url.trim_end_matches('/') != url.trim_end_matches('/') {
+        warn!(
+            "Saved login is for {}, not {}. Run {} again",
+            saved.url,
+            url,
+            "jellyfin-rpc login".green()
+        );
+        return None;
+    }
+
+    debug!("Using saved login for {}", saved.credentials.user_name);
+    Some(saved.credentials)
+}
+
+/// Logs into the configured server and saves the access token to `path`.
+pub fn run(
+    jellyfin: &Jellyfin,
+    path: &str,
+    username: Option<String>,
+    quick_connect: bool,
+    device_name: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Logging in again keeps the same device instead of adding another one to the dashboard
+    let mut device = load(path, &jellyfin.url)
+        .map(|credentials| credentials.device)
+        .unwrap_or_else(Device::this_machine);
+
+    if let Some(device_name) = device_name {
+        device.name = device_name;
+    }
+
+    let login = Login::new(
+        &jellyfin.url,
+        jellyfin.server_kind,
+        jellyfin.self_signed_cert,
+        device,
+    )?;
+
+    let credentials = if quick_connect {
+        let request = login.quick_connect()?;
+
+        info!(
+            "Enter the code {} on the Quick Connect page of a logged in client",
+            request.code.bold()
+        );
+
+        let started = Instant::now();
+
+        loop {
+            if let Some(credentials) = login.poll_quick_connect(&request)? {
+                break credentials;
+            }
+
+            if started.elapsed() >= QUICK_CONNECT_TIMEOUT {
+                return Err(format!(
+                    "the Quick Connect code wasn't entered within {} minutes",
+                    QUICK_CONNECT_TIMEOUT.as_secs() / 60
+                )
+                .into());
+            }
+
+            std::thread::sleep(Duration::from_secs(5));
+        }
+    } else {
+        let username = match username {
+            Some(username) => username,
+            None => prompt("Username: ")?,
+        };
+        let password = rpassword::prompt_password("Password: ")?;
+
+        login.authenticate_by_name(&username, &password)?
+    };
+
+    if let Some(parent) = Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let saved = SavedLogin {
+        url: jellyfin.url.clone(),
+        credentials,
+    };
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    // The token works like a password, keep other users from reading it
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+
+    // The mode only applies to new files, a token saved by an older version may be readable
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(serde_json::to_string_pretty(&saved)?.as_bytes())?;
+
+    info!(
+        "Logged in as {} on {}, the access token was saved to {}",
+        saved.credentials.user_name.green(),
+        saved.credentials.device.name,
+        path
+    );
+
+    Ok(())
+}
+
+fn prompt(text: &str) -> io::Result<String> {
+    print!("{}", text);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
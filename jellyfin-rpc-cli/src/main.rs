@@ -1,13 +1,17 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use colored::Colorize;
-use config::{get_config_path, get_urls_path, Config};
-use jellyfin_rpc::{Client, DisplayFormat, EpisodeDisplayOptions, VERSION};
-use log::{debug, error, info};
+use config::{get_config_path, get_login_path, get_urls_path, Config};
+use jellyfin_rpc::{
+    ActivityOutcome, Client, DisplayFormat, EpisodeDisplayOptions, JfError, MediaType, VERSION,
+};
+use log::{debug, error, info, warn};
 use retry::retry_with_index;
 use simple_logger::SimpleLogger;
-use std::{thread::sleep, time::Duration};
+use std::time::Duration;
 use time::macros::format_description;
+mod cache;
 mod config;
+mod login;
 #[cfg(feature = "updates")]
 mod updates;
 
@@ -25,13 +29,13 @@ struct Args {
     #[arg(
         short = 'i',
         long = "image-urls-file",
-        help = "Path to image urls file for imgur"
+        help = "Path to the cache of uploaded image urls"
     )]
     image_urls: Option<String>,
     #[arg(
         short = 't',
         long = "wait-time",
-        help = "Time to wait between loops in seconds",
+        help = "Time to wait between loops in seconds, only used when no websocket updates arrive",
         default_value_t = 7
     )]
     wait_time: usize,
@@ -42,6 +46,72 @@ struct Args {
         default_value_t = String::from("info")
     )]
     log_level: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Log in as a user instead of using an api key, the access token is saved next to the config
+    Login {
+        #[arg(
+            short = 'u',
+            long = "username",
+            help = "Username to log in as, asked for if left out"
+        )]
+        username: Option<String>,
+        #[arg(
+            short = 'q',
+            long = "quick-connect",
+            help = "Log in with a Quick Connect code instead of a password"
+        )]
+        quick_connect: bool,
+        #[arg(
+            short = 'd',
+            long = "device-name",
+            help = "Name shown in the Jellyfin dashboard, defaults to the hostname"
+        )]
+        device_name: Option<String>,
+    },
+    /// List the placeholders that can be used in the display options
+    Placeholders {
+        #[arg(
+            help = "Only list the placeholders of this media type, e.g. music, movie or episode"
+        )]
+        media_type: Option<String>,
+    },
+    /// Inspect or clean up the cache of images uploaded to image hosts
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// List every cached image
+    List,
+    /// Remove expired images, deleting them from imgur when possible
+    Prune {
+        #[arg(
+            short = 'o',
+            long = "older-than",
+            help = "Also remove images uploaded more than this many days ago"
+        )]
+        older_than: Option<u64>,
+    },
+    /// Remove every cached image, deleting them from imgur when possible
+    Clear {
+        #[arg(help = "Only remove the images of this image host, e.g. imgur or litterbox")]
+        host: Option<String>,
+        #[arg(
+            long = "forget",
+            help = "Also remove images that can't be deleted from their host, leaving them there"
+        )]
+        forget: bool,
+    },
+    /// Count the cached images of every image host
+    Stats,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -60,6 +130,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init()
         .unwrap();
 
+    if let Some(Command::Placeholders { media_type }) = &args.command {
+        return print_placeholders(media_type.as_deref());
+    }
+
+    if let Some(Command::Cache { action }) = args.command {
+        let urls_path = args.image_urls.unwrap_or(get_urls_path()?);
+        return cache::run(action, &urls_path, args.config);
+    }
+
     info!("Initializing Jellyfin-RPC");
 
     #[cfg(feature = "updates")]
@@ -85,14 +164,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    if let Some(Command::Login {
+        username,
+        quick_connect,
+        device_name,
+    }) = args.command
+    {
+        return login::run(
+            &conf.jellyfin,
+            &get_login_path()?,
+            username,
+            quick_connect,
+            device_name,
+        );
+    }
+
     debug!("Creating jellyfin-rpc client builder");
     let mut builder = Client::builder();
 
+    let mut api_key = conf.jellyfin.api_key;
+    let mut user_ids = conf.jellyfin.user_ids;
+
+    if api_key.is_empty() {
+        debug!("No api key in the config, looking for a saved login");
+
+        if let Some(credentials) = login::load(&get_login_path()?, &conf.jellyfin.url) {
+            api_key = credentials.access_token;
+            user_ids.push(credentials.user_id);
+            builder.device(credentials.device);
+        }
+    }
+
     builder
-        .api_key(conf.jellyfin.api_key)
+        .api_key(api_key)
         .url(conf.jellyfin.url)
+        .server_kind(conf.jellyfin.server_kind)
         .usernames(conf.jellyfin.username)
+        .user_ids(user_ids)
+        .servers(conf.jellyfin.servers)
+        .session_priority(conf.jellyfin.session_priority)
+        .combine_sessions(conf.jellyfin.combine_sessions)
+        .allow_sessions(conf.jellyfin.allow)
+        .deny_sessions(conf.jellyfin.deny)
+        .local_only(conf.jellyfin.local_only)
+        .local_device_ids(conf.jellyfin.local_device_ids)
         .self_signed(conf.jellyfin.self_signed_cert)
+        .websocket(conf.jellyfin.websocket)
         .episode_simple(conf.jellyfin.show_simple)
         .episode_divider(conf.jellyfin.add_divider)
         .episode_prefix(conf.jellyfin.append_prefix)
@@ -102,8 +219,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .use_litterbox(conf.images.litterbox_images)
         .process_images(conf.images.process_images)
         .large_image_text(format!("Jellyfin-RPC v{}", VERSION.unwrap_or("UNKNOWN")))
-        .imgur_urls_file_location(args.image_urls.clone().unwrap_or(get_urls_path()?))
-        .litterbox_urls_file_location(args.image_urls.unwrap_or(get_urls_path()?));
+        .image_cache_location(args.image_urls.unwrap_or(get_urls_path()?));
+
+    for (media_type, sources) in conf.images.sources {
+        debug!("Found config.images.sources.{}", media_type);
+        builder.image_sources(media_type, sources);
+    }
 
     if let Some(display) = conf.jellyfin.music.display {
         debug!("Found config.jellyfin.music.display");
@@ -157,6 +278,51 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         builder.episodes_status_display_type(status_display_type);
     }
 
+    if let Some(display) = conf.jellyfin.livetv.display {
+        debug!("Found config.jellyfin.livetv.display");
+        builder.livetv_display(display);
+    }
+
+    if let Some(separator) = conf.jellyfin.livetv.separator {
+        debug!("Found config.jellyfin.livetv.separator");
+        builder.livetv_separator(separator);
+    }
+
+    if let Some(status_display_type) = conf.jellyfin.livetv.status_display_type {
+        debug!("Found config.jellyfin.livetv.status_display_type");
+        builder.livetv_status_display_type(status_display_type);
+    }
+
+    if let Some(display) = conf.jellyfin.books.display {
+        debug!("Found config.jellyfin.books.display");
+        builder.books_display(display);
+    }
+
+    if let Some(separator) = conf.jellyfin.books.separator {
+        debug!("Found config.jellyfin.books.separator");
+        builder.books_separator(separator);
+    }
+
+    if let Some(status_display_type) = conf.jellyfin.books.status_display_type {
+        debug!("Found config.jellyfin.books.status_display_type");
+        builder.books_status_display_type(status_display_type);
+    }
+
+    if let Some(display) = conf.jellyfin.audiobooks.display {
+        debug!("Found config.jellyfin.audiobooks.display");
+        builder.audiobooks_display(display);
+    }
+
+    if let Some(separator) = conf.jellyfin.audiobooks.separator {
+        debug!("Found config.jellyfin.audiobooks.separator");
+        builder.audiobooks_separator(separator);
+    }
+
+    if let Some(status_display_type) = conf.jellyfin.audiobooks.status_display_type {
+        debug!("Found config.jellyfin.audiobooks.status_display_type");
+        builder.audiobooks_status_display_type(status_display_type);
+    }
+
     if let Some(media_types) = conf.jellyfin.blacklist.media_types {
         debug!("Found config.jellyfin.blacklist.media_types");
         debug!("Blacklisted MediaTypes: {:?}", media_types);
@@ -185,7 +351,48 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     debug!("Building client");
-    let mut client = builder.build()?;
+    let mut client = match builder.build() {
+        Ok(client) => client,
+        Err(err @ JfError::InvalidDisplayFormat { .. }) => {
+            error!("{}", err);
+            error!(
+                "Please fix the display options in the config file: {}",
+                "https://github.com/Radiicall/jellyfin-rpc/wiki/Setup".green()
+            );
+            std::process::exit(1)
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    info!("Checking the Jellyfin config");
+    let report = client.verify();
+
+    for server in &report.servers {
+        if let Some(version) = &server.version {
+            info!(
+                "Found {} {} at {}",
+                server.product_name.as_deref().unwrap_or("Jellyfin Server"),
+                version,
+                server.server_name.as_deref().unwrap_or(server.url.as_str())
+            );
+        }
+
+        for problem in &server.problems {
+            if problem.is_fatal() {
+                error!("{}: {}", server.url, problem);
+            } else {
+                warn!("{}: {}", server.url, problem);
+            }
+        }
+    }
+
+    if report.is_fatal() {
+        error!(
+            "Please fix the config file: {}",
+            "https://github.com/Radiicall/jellyfin-rpc/wiki/Setup".green()
+        );
+        std::process::exit(1)
+    }
 
     info!("Connecting to Discord");
     retry_with_index(
@@ -204,30 +411,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     .unwrap();
     info!("Connected!");
 
-    let mut currently_playing = String::new();
-
     loop {
-        sleep(Duration::from_secs(args.wait_time as u64));
+        client.wait_for_update(Duration::from_secs(args.wait_time as u64));
 
         match client.set_activity() {
-            Ok(activity) => {
-                if activity.is_empty() && !currently_playing.is_empty() {
-                    let _ = client.clear_activity();
-                    info!("Cleared activity");
-                    currently_playing = activity;
-                } else if activity != currently_playing {
-                    currently_playing = activity;
-
-                    info!("{}", currently_playing);
-                }
+            Ok(ActivityOutcome::Updated(presence)) => info!("{}", presence.summary()),
+            Ok(ActivityOutcome::Cleared) => info!("Cleared activity"),
+            Ok(outcome) => debug!("{:?}", outcome),
+            Err(JfError::AuthenticationRejected) => {
+                error!(
+                    "Jellyfin rejected the api key, check config.jellyfin.api_key or run {}",
+                    "jellyfin-rpc login".green()
+                );
             }
-            Err(err) => {
-                // TODO: There has to be a better way to check this..
-                if err.to_string() == "content is blacklisted" {
-                    debug!("{}", err);
-                    continue;
-                }
-
+            Err(err @ (JfError::Jellyfin(_) | JfError::JellyfinStatus(_))) => {
+                // Jellyfin being unreachable has nothing to do with Discord, try again next loop
+                error!("{}", err);
+                debug!("{:?}", err);
+            }
+            Err(JfError::Discord(err)) => {
                 error!("{}", err);
                 debug!("{:?}", err);
                 retry_with_index(
@@ -246,6 +448,60 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .unwrap();
                 info!("Reconnected!");
             }
+            Err(err) => {
+                error!("{}", err);
+                debug!("{:?}", err);
+            }
         }
     }
 }
+
+fn print_placeholders(media_type: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    let media_types = match media_type {
+        Some(media_type) => match MediaType::from(media_type.to_lowercase()) {
+            MediaType::None => {
+                error!("Unknown media type: {}", media_type.red());
+                std::process::exit(1)
+            }
+            media_type => vec![media_type],
+        },
+        None => vec![
+            MediaType::Music,
+            MediaType::Movie,
+            MediaType::Episode,
+            MediaType::LiveTv,
+            MediaType::Book,
+            MediaType::AudioBook,
+            MediaType::MusicVideo,
+            MediaType::Video,
+            MediaType::Trailer,
+            MediaType::Recording,
+            MediaType::Photo,
+            MediaType::ChannelItem,
+        ],
+    };
+
+    for media_type in media_types {
+        println!("{}", media_type.to_string().bold());
+
+        for placeholder in jellyfin_rpc::placeholders(media_type) {
+            println!(
+                "  {:<20} {} (e.g. {})",
+                format!("{{{}}}", placeholder.name).green(),
+                placeholder.description,
+                placeholder.example
+            );
+        }
+
+        println!();
+    }
+
+    println!(
+        "{} inserts the separator and {} any field of the Jellyfin item, e.g. {}",
+        "{sep}".green(),
+        "{raw:Field}".green(),
+        "{raw:OfficialRating}".green()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,468 @@
+use crate::external::{host, image_utils::prepare_image};
+use crate::image::JellyfinImage;
+use crate::jellyfin::{Listing, Program, RawSession, SystemInfo, User, VirtualFolder};
+use crate::server::Connection;
+use crate::socket::UpdateSignal;
+use crate::{ActivityOutcome, ClientBuilder, Core, JfError, JfResult, VerifyReport};
+use discord_rich_presence::{DiscordIpc, DiscordIpcClient};
+use log::{debug, warn};
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex, PoisonError};
+use url::Url;
+
+/// Async version of `Client`, built with `ClientBuilder::build_async()`.
+///
+/// Jellyfin is queried with the non-blocking reqwest client. The Discord IPC socket, image hosts,
+/// image cache and image processing are blocking so they run on tokio's blocking thread pool.
+pub struct AsyncClient {
+    discord_ipc_client: Arc<Mutex<DiscordIpcClient>>,
+    /// One per server in `core.servers`
+    connections: Vec<Connection<reqwest::Client>>,
+    update_signal: Arc<UpdateSignal>,
+    pub(crate) core: Core,
+}
+
+impl AsyncClient {
+    pub(crate) fn new(client_id: &str, core: Core) -> JfResult<Self> {
+        let connections = core
+            .servers
+            .iter()
+            .map(|server| {
+                Ok(Connection::new(
+                    reqwest::Client::builder()
+                        .default_headers(server.headers.clone())
+                        .danger_accept_invalid_certs(server.self_signed)
+                        .build()?,
+                ))
+            })
+            .collect::<JfResult<_>>()?;
+
+        Ok(Self {
+            discord_ipc_client: Arc::new(Mutex::new(DiscordIpcClient::new(client_id))),
+            connections,
+            update_signal: Arc::default(),
+            core,
+        })
+    }
+
+    /// Calls the `ClientBuilder::new()` function
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Connects to the discord socket
+    pub async fn connect(&mut self) -> JfResult<()> {
+        self.discord(|discord| discord.connect()).await?;
+        // A new Discord client doesn't have the activity yet
+        self.core.last_presence = None;
+        Ok(())
+    }
+
+    /// Reconnects to the discord socket
+    pub async fn reconnect(&mut self) -> JfResult<()> {
+        self.discord(|discord| discord.reconnect()).await?;
+        self.core.last_presence = None;
+        Ok(())
+    }
+
+    /// Clears current activity on discord if anything is being displayed
+    ///
+    /// # Example
+    /// ```no_run
+    /// use jellyfin_rpc::AsyncClient;
+    ///
+    /// # async fn run() {
+    /// let mut builder = AsyncClient::builder();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let mut client = builder.build_async().unwrap();
+    ///
+    /// client.connect().await.unwrap();
+    ///
+    /// client.set_activity().await.unwrap();
+    ///
+    /// client.clear_activity().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn clear_activity(&mut self) -> JfResult<()> {
+        self.discord(|discord| discord.clear_activity()).await?;
+        self.core.last_presence = None;
+        Ok(())
+    }
+
+    /// Gathers information from jellyfin about what is being played and displays it according to the options supplied to the builder.
+    ///
+    /// Returns what happened to the activity, see `Client::set_activity`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use jellyfin_rpc::AsyncClient;
+    ///
+    /// # async fn run() {
+    /// let mut builder = AsyncClient::builder();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let mut client = builder.build_async().unwrap();
+    ///
+    /// client.connect().await.unwrap();
+    ///
+    /// client.set_activity().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn set_activity(&mut self) -> JfResult<ActivityOutcome> {
+        let outcome = self.build_presence().await?;
+
+        match outcome {
+            ActivityOutcome::Updated(presence) => {
+                // Discord only allows a few updates every 20 seconds, so unchanged presences aren't sent again
+                let outcome = self.core.presence_shown(presence);
+
+                if let ActivityOutcome::Updated(presence) = &outcome {
+                    let activity = presence.clone();
+                    if let Err(err) = self
+                        .discord(move |discord| discord.set_activity(activity.to_activity()))
+                        .await
+                    {
+                        self.core.last_presence = None;
+                        return Err(err);
+                    }
+                }
+
+                Ok(outcome)
+            }
+            outcome => {
+                if self.core.last_presence.take().is_some() {
+                    self.discord(|discord| discord.clear_activity()).await?;
+                    return Ok(ActivityOutcome::Cleared);
+                }
+
+                Ok(outcome)
+            }
+        }
+    }
+
+    /// Checks that every server can be reached, accepts the credentials and knows the configured users,
+    /// see `Client::verify`.
+    pub async fn verify(&self) -> VerifyReport {
+        let mut servers = Vec::new();
+
+        for server in 0..self.core.servers.len() {
+            let info = self.fetch_system_info(server).await;
+            let users = match info {
+                Ok(_) => self.fetch_users(server).await,
+                Err(_) => Ok(Vec::new()),
+            };
+
+            servers.push(self.core.servers[server].report(info, users));
+        }
+
+        VerifyReport { servers }
+    }
+
+    /// Gathers information from jellyfin about what is being played without touching Discord,
+    /// see `Client::build_presence`.
+    pub async fn build_presence(&mut self) -> JfResult<ActivityOutcome> {
+        // Make sure the blacklist caches are loaded/valid, blacklisted sessions are passed over
+        for server in 0..self.core.servers.len() {
+            if self.core.servers[server].blacklist_expired() {
+                self.reload_server_blacklist(server).await;
+            }
+        }
+
+        self.get_session().await?;
+
+        if let Some(outcome) = self.core.check_session()? {
+            return Ok(outcome);
+        }
+
+        let image_url = self.get_image_url().await?;
+
+        let presence = self.core.build_presence(image_url)?;
+
+        Ok(ActivityOutcome::Updated(Box::new(presence)))
+    }
+
+    /// Reload the library list from Jellyfin and filter out the user-provided blacklisted libraries
+    pub async fn reload_blacklist(&mut self) {
+        for server in 0..self.core.servers.len() {
+            self.reload_server_blacklist(server).await;
+        }
+    }
+
+    async fn reload_server_blacklist(&mut self, server: usize) {
+        let virtual_folders = self.fetch_virtual_folders(server).await;
+        self.core.servers[server].set_blacklist(virtual_folders);
+    }
+
+    async fn fetch_virtual_folders(&self, server: usize) -> JfResult<Vec<VirtualFolder>> {
+        Ok(self.connections[server]
+            .reqwest
+            .get(
+                self.core.servers[server]
+                    .url
+                    .join("Library/VirtualFolders")?,
+            )
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Listing<VirtualFolder>>()
+            .await?
+            .into())
+    }
+
+    async fn get_session(&mut self) -> JfResult<()> {
+        let mut sessions = Vec::new();
+        let mut errors = Vec::new();
+
+        for server in 0..self.core.servers.len() {
+            match self.get_server_sessions(server).await {
+                Ok(server_sessions) => sessions.push(server_sessions),
+                Err(err) => {
+                    sessions.push(Vec::new());
+                    errors.push((server, err));
+                }
+            }
+        }
+
+        // Only give up if none of the servers could be reached
+        if errors.len() == self.core.servers.len() {
+            let (_, err) = errors.swap_remove(0);
+            return Err(err);
+        }
+
+        for (server, err) in errors {
+            warn!(
+                "Failed to get sessions from {}: {}",
+                self.core.servers[server].url, err
+            );
+        }
+
+        self.core.select_session(sessions);
+
+        if let Some(channel) = self.core.program_needed() {
+            let program = self.fetch_program(&channel).await.unwrap_or_else(|err| {
+                debug!(
+                    "Couldn't look up the programme on channel {}: {}",
+                    channel, err
+                );
+                None
+            });
+            self.core.set_program(channel, program);
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_program(&self, channel: &str) -> JfResult<Option<Program>> {
+        let programs: Vec<Program> = self
+            .session_reqwest()
+            .get(self.core.servers[self.core.session_server].program_url(channel)?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Listing<Program>>()
+            .await?
+            .into();
+
+        Ok(programs.into_iter().next())
+    }
+
+    async fn get_server_sessions(&mut self, server: usize) -> JfResult<Vec<RawSession>> {
+        if !self.core.servers[server].users_resolved() {
+            let users = self.fetch_users(server).await;
+            self.core.servers[server].resolve_users(users);
+        }
+
+        let connection = &mut self.connections[server];
+        let server = &self.core.servers[server];
+
+        if let Some(sessions) = connection
+            .session_socket(server, &self.update_signal)
+            .and_then(|s| s.sessions())
+        {
+            debug!("Using sessions pushed over the websocket");
+            return Ok(sessions);
+        }
+
+        Ok(connection
+            .reqwest
+            .get(server.sessions_url()?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn fetch_system_info(&self, server: usize) -> JfResult<SystemInfo> {
+        Ok(self.connections[server]
+            .reqwest
+            .get(self.core.servers[server].system_info_url()?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    async fn fetch_users(&self, server: usize) -> JfResult<Vec<User>> {
+        Ok(self.connections[server]
+            .reqwest
+            .get(self.core.servers[server].users_url()?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Listing<User>>()
+            .await?
+            .into())
+    }
+
+    /// Http client of the server the current session is on
+    pub(crate) fn session_reqwest(&self) -> &reqwest::Client {
+        &self.connections[self.core.session_server].reqwest
+    }
+
+    /// Picks the image shown on the activity, falls back to the default image if anything goes wrong
+    async fn get_image_url(&self) -> JfResult<Url> {
+        if !self.core.show_images {
+            return self.core.default_image_url();
+        }
+
+        match self.find_image().await {
+            Ok(image_url) => Ok(image_url),
+            Err(err) => {
+                debug!("Couldn't get an image, using default: {}", err);
+                self.core.default_image_url()
+            }
+        }
+    }
+
+    /// Goes through the image sources of the media type until one of them has an image
+    async fn find_image(&self) -> JfResult<Url> {
+        for image in self.core.image_candidates() {
+            let image_url = if self.core.image_hosts.is_empty() {
+                self.get_image(&image).await
+            } else {
+                self.get_hosted_image(&image).await
+            };
+
+            match image_url {
+                Err(JfError::NoImage) => {
+                    debug!("{} doesn't exist, trying the next image", image.path())
+                }
+                image_url => return image_url,
+            }
+        }
+
+        Err(JfError::NoImage)
+    }
+
+    /// Uploads the image from Jellyfin to the image hosts, unless it has been uploaded before.
+    ///
+    /// The cache, image processing and image hosts are blocking so they run on tokio's blocking thread pool.
+    async fn get_hosted_image(&self, image: &JellyfinImage) -> JfResult<Url> {
+        let cache_id = image.cache_id();
+        let image_tag = image.tag.clone();
+        let image_hosts = self.core.image_hosts.clone();
+        let image_cache = self.core.image_cache.clone();
+
+        let cached = {
+            let (image_hosts, image_cache) = (image_hosts.clone(), image_cache.clone());
+            let (cache_id, image_tag) = (cache_id.clone(), image_tag.clone());
+
+            tokio::task::spawn_blocking(move || {
+                host::find_cached(
+                    &image_hosts,
+                    image_cache.as_deref(),
+                    &cache_id,
+                    image_tag.as_deref(),
+                )
+            })
+            .await
+            .map_err(joined)??
+        };
+
+        if let Some(image_url) = cached {
+            return Ok(image_url);
+        }
+
+        let image_bytes = self
+            .session_reqwest()
+            .get(self.get_image(image).await?)
+            .send()
+            .await?
+            .bytes()
+            .await?;
+        let process_images = self.core.process_images;
+
+        tokio::task::spawn_blocking(move || {
+            let image_bytes = prepare_image(process_images, &image_bytes)?;
+
+            host::upload(
+                &image_hosts,
+                image_cache.as_deref(),
+                &cache_id,
+                image_tag.as_deref(),
+                image_bytes,
+            )
+        })
+        .await
+        .map_err(joined)?
+    }
+
+    /// Url to `image` on Jellyfin, asks the server whether it exists when the tag isn't known
+    async fn get_image(&self, image: &JellyfinImage) -> JfResult<Url> {
+        let image_url = self.core.image_url(image)?;
+
+        if image.tag.is_some() {
+            return Ok(image_url);
+        }
+
+        let response = self
+            .session_reqwest()
+            .head(image_url.as_ref())
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(JfError::NoImage);
+        }
+
+        response.error_for_status()?;
+        Ok(image_url)
+    }
+
+    /// Runs a call on the blocking Discord IPC client without blocking the async runtime
+    async fn discord<F>(&self, call: F) -> JfResult<()>
+    where
+        F: FnOnce(&mut DiscordIpcClient) -> Result<(), discord_rich_presence::error::Error>
+            + Send
+            + 'static,
+    {
+        let discord_ipc_client = self.discord_ipc_client.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let mut discord_ipc_client = discord_ipc_client
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            call(&mut discord_ipc_client)
+        })
+        .await
+        .map_err(joined)?;
+
+        Ok(result?)
+    }
+}
+
+/// Passes a panic in a blocking task on to the caller, cancelled tasks become an error
+fn joined(err: tokio::task::JoinError) -> JfError {
+    match err.try_into_panic() {
+        Ok(panic) => std::panic::resume_unwind(panic),
+        Err(_) => JfError::Cancelled,
+    }
+}
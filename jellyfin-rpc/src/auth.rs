@@ -0,0 +1,211 @@
+use crate::jellyfin::{AuthenticationResult, QuickConnectState};
+use crate::server;
+use crate::{JfResult, ServerKind, VERSION};
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+use url::{form_urlencoded, Url};
+
+/// Client name shown next to the device in the Jellyfin dashboard.
+const CLIENT_NAME: &str = "Jellyfin-RPC";
+
+/// Describes the machine jellyfin-rpc runs on to the server,
+/// it's listed on the Devices page of the dashboard under this name.
+///
+/// Access tokens from [`Login`] belong to the device they were created for,
+/// so the same device has to be passed to `ClientBuilder::device()` when using them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Device {
+    pub name: String,
+    pub id: String,
+}
+
+impl Device {
+    /// A device named after this machine's hostname with a newly generated id.
+    pub fn this_machine() -> Self {
+        let name = gethostname::gethostname()
+            .into_string()
+            .unwrap_or_else(|_| CLIENT_NAME.to_string());
+
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        SystemTime::now().hash(&mut hasher);
+        std::process::id().hash(&mut hasher);
+
+        Self {
+            id: format!("jellyfin-rpc-{:016x}", hasher.finish()),
+            name,
+        }
+    }
+}
+
+/// Value of the authorization header, `token` is left out while logging in.
+fn authorization(token: Option<&str>, device: Option<&Device>) -> String {
+    let mut parts = Vec::new();
+
+    if let Some(device) = device {
+        parts.push(format!("Client=\"{}\"", CLIENT_NAME));
+        parts.push(format!("Device=\"{}\"", encode(&device.name)));
+        parts.push(format!("DeviceId=\"{}\"", encode(&device.id)));
+        parts.push(format!("Version=\"{}\"", VERSION.unwrap_or("0.0.0")));
+    }
+
+    if let Some(token) = token {
+        parts.push(format!("Token=\"{}\"", token));
+    }
+
+    format!("MediaBrowser {}", parts.join(", "))
+}
+
+/// Jellyfin url decodes the values in the authorization header, which also keeps it ascii.
+fn encode(value: &str) -> String {
+    form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+/// Headers that authenticate requests with an api key or access token.
+///
+/// Without a device the token alone is sent, like it always has been for api keys.
+pub(crate) fn headers(
+    kind: ServerKind,
+    token: Option<&str>,
+    device: Option<&Device>,
+) -> JfResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    let authorization = authorization(token, device);
+
+    // Emby expects a full client description in the Authorization header,
+    // the token header is enough to authenticate on its own
+    if kind == ServerKind::Jellyfin {
+        headers.insert(AUTHORIZATION, authorization.parse()?);
+    }
+
+    if device.is_some() {
+        headers.insert("X-Emby-Authorization", authorization.parse()?);
+    }
+
+    if let Some(token) = token {
+        headers.insert("X-Emby-Token", token.parse()?);
+    }
+
+    Ok(headers)
+}
+
+/// An access token for a user, tied to the device it was created for.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub access_token: String,
+    pub user_id: String,
+    pub user_name: String,
+    pub device: Device,
+}
+
+/// A pending Quick Connect request, returned by [`Login::quick_connect`].
+#[derive(Clone, Debug)]
+pub struct QuickConnect {
+    /// Code the user has to enter in the Quick Connect page of another logged in client
+    pub code: String,
+    secret: String,
+}
+
+/// Logs into a server as a user, for people who can't create api keys.
+///
+/// # Example
+/// ```no_run
+/// use jellyfin_rpc::{ClientBuilder, Device, Login, ServerKind};
+///
+/// let login = Login::new("https://jellyfin.example.com", ServerKind::Jellyfin, false, Device::this_machine()).unwrap();
+/// let credentials = login.authenticate_by_name("user", "password").unwrap();
+///
+/// let mut builder = ClientBuilder::new();
+/// builder.url("https://jellyfin.example.com")
+///     .api_key(credentials.access_token)
+///     .user_id(credentials.user_id)
+///     .device(credentials.device);
+/// ```
+pub struct Login {
+    url: Url,
+    device: Device,
+    reqwest: reqwest::blocking::Client,
+}
+
+impl Login {
+    pub fn new(url: &str, kind: ServerKind, self_signed: bool, device: Device) -> JfResult<Self> {
+        Ok(Self {
+            url: kind.api_url(server::base_url(url)?)?,
+            reqwest: reqwest::blocking::Client::builder()
+                .default_headers(headers(kind, None, Some(&device))?)
+                .danger_accept_invalid_certs(self_signed)
+                .build()?,
+            device,
+        })
+    }
+
+    /// Logs in with a username and password.
+    pub fn authenticate_by_name(&self, username: &str, password: &str) -> JfResult<Credentials> {
+        let result: AuthenticationResult = self
+            .reqwest
+            .post(self.url.join("Users/AuthenticateByName")?)
+            .json(&serde_json::json!({
+                "Username": username,
+                "Pw": password,
+            }))
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(self.credentials(result))
+    }
+
+    /// Starts a Quick Connect request, show [`QuickConnect::code`] to the user
+    /// and call [`Login::poll_quick_connect`] until it's approved.
+    pub fn quick_connect(&self) -> JfResult<QuickConnect> {
+        let state: QuickConnectState = self
+            .reqwest
+            .post(self.url.join("QuickConnect/Initiate")?)
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(QuickConnect {
+            code: state.code,
+            secret: state.secret,
+        })
+    }
+
+    /// Returns the credentials once the Quick Connect request was approved, `None` while it's still pending.
+    pub fn poll_quick_connect(
+        &self,
+        quick_connect: &QuickConnect,
+    ) -> JfResult<Option<Credentials>> {
+        let mut url = self.url.join("QuickConnect/Connect")?;
+        url.query_pairs_mut()
+            .append_pair("Secret", &quick_connect.secret);
+
+        let state: QuickConnectState = self.reqwest.get(url).send()?.error_for_status()?.json()?;
+
+        if !state.authenticated {
+            return Ok(None);
+        }
+
+        let result: AuthenticationResult = self
+            .reqwest
+            .post(self.url.join("Users/AuthenticateWithQuickConnect")?)
+            .json(&serde_json::json!({ "Secret": quick_connect.secret }))
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(Some(self.credentials(result)))
+    }
+
+    fn credentials(&self, result: AuthenticationResult) -> Credentials {
+        Credentials {
+            access_token: result.access_token,
+            user_id: result.user.id,
+            user_name: result.user.name,
+            device: self.device.clone(),
+        }
+    }
+}
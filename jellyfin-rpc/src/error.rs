@@ -1,26 +1,171 @@
-use std::{error::Error, fmt::Display};
+use reqwest::{header::InvalidHeaderValue, StatusCode};
+use std::{error::Error, fmt::Display, time::SystemTimeError};
 
 /// Error type
 #[derive(Debug)]
 pub enum JfError {
-    /// MediaType returned from jellyfin is of type None,
-    /// this should be reported on github
+    #[deprecated(note = "media types that aren't recognized are displayed with a generic format")]
     UnrecognizedMediaType,
-    /// Content is in blacklist
+    #[deprecated(note = "blacklisted content is reported as `ActivityOutcome::Blacklisted`")]
     ContentBlacklist,
     MissingRequiredValues,
     NoImage,
+    /// Couldn't reach Jellyfin or couldn't read its response
+    Jellyfin(reqwest::Error),
+    /// Jellyfin responded with an unsuccessful status code
+    JellyfinStatus(StatusCode),
+    /// Jellyfin rejected the api key, access token or login credentials
+    AuthenticationRejected,
+    /// Something went wrong while talking to the Discord IPC socket
+    Discord(discord_rich_presence::error::Error),
+    /// An image host failed to upload or delete an image
+    ImageHost {
+        host: &'static str,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The image couldn't be processed before uploading it
+    Image(image::ImageError),
+    /// Reading or writing one of the cache files failed
+    Cache(std::io::Error),
+    /// Reading or writing a file or socket failed
+    Io(std::io::Error),
+    /// A response or file isn't the JSON that was expected
+    Json(serde_json::Error),
+    /// The url isn't valid
+    InvalidUrl(url::ParseError),
+    /// The api key contains characters that can't be sent in a header
+    InvalidApiKey(InvalidHeaderValue),
+    /// The system clock is set to before the unix epoch
+    SystemTime(SystemTimeError),
+    /// A display format couldn't be parsed, `position` is the character the problem starts at
+    InvalidDisplayFormat {
+        format: String,
+        position: usize,
+        reason: String,
+    },
+    /// An image source isn't written as `type` or `item:type`, e.g. `series:primary`
+    InvalidImageSource(String),
+    /// A blocking task of the async client was cancelled, usually because the runtime is shutting down
+    Cancelled,
 }
 
-impl Error for JfError {}
+impl Error for JfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JfError::Jellyfin(err) => Some(err),
+            JfError::Discord(err) => Some(err),
+            JfError::ImageHost { source, .. } => Some(source.as_ref()),
+            JfError::Image(err) => Some(err),
+            JfError::Cache(err) => Some(err),
+            JfError::Io(err) => Some(err),
+            JfError::Json(err) => Some(err),
+            JfError::InvalidUrl(err) => Some(err),
+            JfError::InvalidApiKey(err) => Some(err),
+            JfError::SystemTime(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl Display for JfError {
+    #[allow(deprecated)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             JfError::MissingRequiredValues => write!(f, "missing required values to build client"),
             JfError::UnrecognizedMediaType => write!(f, "unrecognized media type"),
             JfError::ContentBlacklist => write!(f, "content is blacklisted"),
             JfError::NoImage => write!(f, "media does not have an image"),
+            JfError::Jellyfin(err) => write!(f, "jellyfin request failed: {}", err),
+            JfError::JellyfinStatus(status) => write!(f, "jellyfin responded with {}", status),
+            JfError::AuthenticationRejected => write!(f, "jellyfin rejected the credentials"),
+            JfError::Discord(err) => write!(f, "discord ipc error: {}", err),
+            JfError::ImageHost { host, source } => {
+                write!(f, "uploading image to {} failed: {}", host, source)
+            }
+            JfError::Image(err) => write!(f, "failed to process image: {}", err),
+            JfError::Cache(err) => write!(f, "cache error: {}", err),
+            JfError::Io(err) => write!(f, "i/o error: {}", err),
+            JfError::Json(err) => write!(f, "invalid json: {}", err),
+            JfError::InvalidUrl(err) => write!(f, "invalid url: {}", err),
+            JfError::InvalidApiKey(err) => write!(f, "invalid api key: {}", err),
+            JfError::SystemTime(err) => write!(f, "system time error: {}", err),
+            JfError::InvalidDisplayFormat {
+                format,
+                position,
+                reason,
+            } => write!(
+                f,
+                "invalid display format \"{}\": {} at character {}",
+                format, reason, position
+            ),
+            JfError::InvalidImageSource(source) => write!(f, "invalid image source \"{}\"", source),
+            JfError::Cancelled => write!(f, "task was cancelled"),
         }
     }
 }
+
+impl JfError {
+    pub(crate) fn image_host<E: Into<Box<dyn Error + Send + Sync>>>(
+        host: &'static str,
+        source: E,
+    ) -> Self {
+        JfError::ImageHost {
+            host,
+            source: source.into(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for JfError {
+    fn from(err: reqwest::Error) -> Self {
+        match err.status() {
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                JfError::AuthenticationRejected
+            }
+            Some(status) => JfError::JellyfinStatus(status),
+            None => JfError::Jellyfin(err),
+        }
+    }
+}
+
+impl From<discord_rich_presence::error::Error> for JfError {
+    fn from(err: discord_rich_presence::error::Error) -> Self {
+        JfError::Discord(err)
+    }
+}
+
+impl From<image::ImageError> for JfError {
+    fn from(err: image::ImageError) -> Self {
+        JfError::Image(err)
+    }
+}
+
+impl From<std::io::Error> for JfError {
+    fn from(err: std::io::Error) -> Self {
+        JfError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for JfError {
+    fn from(err: serde_json::Error) -> Self {
+        JfError::Json(err)
+    }
+}
+
+impl From<url::ParseError> for JfError {
+    fn from(err: url::ParseError) -> Self {
+        JfError::InvalidUrl(err)
+    }
+}
+
+impl From<InvalidHeaderValue> for JfError {
+    fn from(err: InvalidHeaderValue) -> Self {
+        JfError::InvalidApiKey(err)
+    }
+}
+
+impl From<SystemTimeError> for JfError {
+    fn from(err: SystemTimeError) -> Self {
+        JfError::SystemTime(err)
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+use chrono::Utc;
+use fs2::FileExt;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    external::host::{ImageHost, UploadedImage},
+    JfError, JfResult,
+};
+
+/// Version of the cache file written by this version of the library.
+///
+/// Version 1 was a plain list of images, see [`migrate`].
+const SCHEMA_VERSION: u32 = 2;
+
+/// How long litterbox kept images that were cached before expiry times were stored
+const LEGACY_LITTERBOX_EXPIRY: i64 = 72 * 60 * 60;
+
+/// An uploaded image in the [`ImageCache`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CachedImage {
+    pub url: String,
+    /// Unix timestamp of the upload, `0` if an older version cached it without one
+    pub uploaded_at: i64,
+    /// Unix timestamp of when the image stops being used, `None` if it never does
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Needed by [`ImageHost::delete`](crate::ImageHost::delete) to remove the image from its host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_handle: Option<String>,
+}
+
+impl CachedImage {
+    pub fn has_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// An image in the [`ImageCache`] and where it's stored, see [`ImageCache::images`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    /// Name of the image host it was uploaded to
+    pub host: String,
+    /// Id of the Jellyfin item followed by the tag of its image, e.g. `"4f1c…:9a2b…"`
+    pub key: String,
+    pub image: CachedImage,
+}
+
+/// Images of one image host in the [`ImageCache`], see [`ImageCache::stats`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CacheStats {
+    pub host: String,
+    pub images: usize,
+    pub expired: usize,
+    /// Images that can be deleted from the host with their delete handle
+    pub deletable: usize,
+    /// Unix timestamp of the oldest upload with a known time
+    pub oldest_upload: Option<i64>,
+}
+
+/// What [`ImageCache::prune`] and [`ImageCache::clear`] did.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Removed {
+    /// Images removed from the cache
+    pub removed: usize,
+    /// Images that were also deleted from their host
+    pub deleted: usize,
+    /// Images that couldn't be deleted from their host or whose host wasn't given,
+    /// they're kept in the cache to try again later
+    pub failed: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    version: u32,
+    /// Images per image host, keyed by item id and image tag
+    namespaces: BTreeMap<String, BTreeMap<String, CachedImage>>,
+}
+
+/// The cache as it was last read or written
+struct State {
+    file: CacheFile,
+    /// Version of the file at that point, `None` if it didn't exist
+    version: Option<FileVersion>,
+}
+
+/// Tells whether the file changed, every write replaces the file so its inode changes as well
+/// as the modification time, which might not if two writes follow each other closely.
+#[derive(PartialEq)]
+struct FileVersion {
+    modified: SystemTime,
+    len: u64,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+/// Entry of the list of images that version 1 stored
+#[derive(Deserialize)]
+struct LegacyImage {
+    id: String,
+    url: String,
+    /// Upload time, only litterbox stored it
+    timestamp: Option<String>,
+}
+
+/// Urls of images uploaded to image hosts, so the same image isn't uploaded again.
+///
+/// Every host has its own namespace in the cache, named after [`ImageHost::name`](crate::ImageHost::name).
+/// Every change is written to a temporary file which then replaces it, so the cache can't be left half written.
+///
+/// Several processes can share the file, e.g. the running client and `jellyfin-rpc cache prune`.
+/// The file is read again when another process changed it, and changes are made to what's on disk
+/// while holding a lock on `<location>.lock`, so they don't overwrite each other.
+pub struct ImageCache {
+    location: PathBuf,
+    state: Mutex<State>,
+}
+
+impl ImageCache {
+    /// Reads the cache at `location`, a missing file is an empty cache.
+    ///
+    /// Caches written by older versions are migrated,
+    /// caches written by newer versions can't be read and return an error.
+    pub fn open<P: Into<PathBuf>>(location: P) -> JfResult<Self> {
+        let location = location.into();
+        let state = read(&location)?;
+
+        Ok(Self {
+            location,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn location(&self) -> &Path {
+        &self.location
+    }
+
+    /// The cache as it is on disk, read again if another process changed the file
+    fn lock(&self) -> MutexGuard<'_, State> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if version(&self.location) != state.version {
+            match read(&self.location) {
+                Ok(read) => *state = read,
+                Err(err) => warn!("Couldn't read the changed image cache: {}", err),
+            }
+        }
+
+        state
+    }
+
+    /// Applies `change` to the cache as it is on disk while holding the lock on the file,
+    /// `change` returns whether anything changed and the file has to be written.
+    fn modify<T, F>(&self, change: F) -> JfResult<T>
+    where
+        F: FnOnce(&mut CacheFile) -> (T, bool),
+    {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let lock = self.lock_file()?;
+
+        *state = read(&self.location)?;
+
+        let (result, changed) = change(&mut state.file);
+        if changed {
+            self.write(&mut state)?;
+        }
+
+        lock.unlock().map_err(JfError::Cache)?;
+
+        Ok(result)
+    }
+
+    /// Locks `<location>.lock`, the cache file itself is replaced on every write
+    fn lock_file(&self) -> JfResult<File> {
+        if let Some(parent) = self.location.parent() {
+            fs::create_dir_all(parent).map_err(JfError::Cache)?;
+        }
+
+        let mut path = self.location.clone().into_os_string();
+        path.push(".lock");
+
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(JfError::Cache)?;
+        lock.lock_exclusive().map_err(JfError::Cache)?;
+
+        Ok(lock)
+    }
+
+    /// Url of the image of `item_id` uploaded to the host called `namespace`,
+    /// expired images are removed from the cache instead.
+    pub(crate) fn get(
+        &self,
+        namespace: &str,
+        item_id: &str,
+        image_tag: Option<&str>,
+    ) -> JfResult<Option<Url>> {
+        let now = Utc::now().timestamp();
+
+        // Images cached before image tags were stored only have the item id
+        let keys = [key(item_id, image_tag), item_id.to_string()];
+
+        let expired: Vec<&String> = {
+            let state = self.lock();
+            let Some(images) = state.file.namespaces.get(namespace) else {
+                return Ok(None);
+            };
+
+            let expired: Vec<&String> = keys
+                .iter()
+                .filter(|key| images.get(*key).is_some_and(|image| image.has_expired(now)))
+                .collect();
+
+            if expired.is_empty() {
+                return find(namespace, images, &keys);
+            }
+
+            expired
+        };
+
+        self.modify(|file| {
+            let Some(images) = file.namespaces.get_mut(namespace) else {
+                return (Ok(None), false);
+            };
+
+            let mut changed = false;
+            for key in expired {
+                if images.get(key).is_some_and(|image| image.has_expired(now)) {
+                    debug!("Removing expired {} image of {}", namespace, key);
+                    images.remove(key);
+                    changed = true;
+                }
+            }
+
+            (find(namespace, images, &keys), changed)
+        })?
+    }
+
+    /// Stores an image that was just uploaded to the host called `namespace`,
+    /// `ttl` is used if the host didn't say when the image expires.
+    pub(crate) fn insert(
+        &self,
+        namespace: &str,
+        item_id: &str,
+        image_tag: Option<&str>,
+        uploaded: &UploadedImage,
+        ttl: Option<Duration>,
+    ) -> JfResult<()> {
+        let now = Utc::now().timestamp();
+        let image = CachedImage {
+            url: uploaded.url.to_string(),
+            uploaded_at: now,
+            expires_at: uploaded
+                .expires_at
+                .or(ttl.map(|ttl| now + ttl.as_secs() as i64)),
+            delete_handle: uploaded.delete_handle.clone(),
+        };
+
+        self.modify(|file| {
+            file.namespaces
+                .entry(namespace.to_string())
+                .or_default()
+                .insert(key(item_id, image_tag), image);
+
+            ((), true)
+        })
+    }
+
+    /// Every image in the cache, sorted by host and key
+    pub fn images(&self) -> Vec<CacheEntry> {
+        self.lock()
+            .file
+            .namespaces
+            .iter()
+            .flat_map(|(host, images)| {
+                images.iter().map(move |(key, image)| CacheEntry {
+                    host: host.clone(),
+                    key: key.clone(),
+                    image: image.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Number of images in the cache per image host
+    pub fn stats(&self) -> Vec<CacheStats> {
+        let now = Utc::now().timestamp();
+
+        self.lock()
+            .file
+            .namespaces
+            .iter()
+            .map(|(host, images)| CacheStats {
+                host: host.clone(),
+                images: images.len(),
+                expired: images.values().filter(|i| i.has_expired(now)).count(),
+                deletable: images
+                    .values()
+                    .filter(|i| i.delete_handle.is_some())
+                    .count(),
+                oldest_upload: images
+                    .values()
+                    .map(|i| i.uploaded_at)
+                    .filter(|&uploaded_at| uploaded_at > 0)
+                    .min(),
+            })
+            .collect()
+    }
+
+    /// Removes images that have expired, and images uploaded longer than `older_than` ago.
+    /// Images without a known upload time count as old.
+    ///
+    /// Images that have a delete handle are deleted from their host,
+    /// they're kept if it isn't in `hosts`.
+    pub fn prune(
+        &self,
+        older_than: Option<Duration>,
+        hosts: &[&dyn ImageHost],
+    ) -> JfResult<Removed> {
+        let now = Utc::now().timestamp();
+        let cutoff = older_than.map(|age| now - age.as_secs() as i64);
+
+        self.remove(hosts, false, |_, image| {
+            image.has_expired(now) || cutoff.is_some_and(|cutoff| image.uploaded_at <= cutoff)
+        })
+    }
+
+    /// Removes every image, or only the images uploaded to the host called `host`.
+    ///
+    /// Images that have a delete handle are deleted from their host, if it isn't in `hosts`
+    /// they're kept unless `forget` is set, which leaves them on the host.
+    pub fn clear(
+        &self,
+        host: Option<&str>,
+        hosts: &[&dyn ImageHost],
+        forget: bool,
+    ) -> JfResult<Removed> {
+        self.remove(hosts, forget, |namespace, _| {
+            host.is_none_or(|host| host == namespace)
+        })
+    }
+
+    /// Deleting an image from its host can take a while, so it's done without holding the lock
+    /// and the images are only removed from the cache afterwards, unless they changed meanwhile.
+    fn remove<F>(
+        &self,
+        hosts: &[&dyn ImageHost],
+        forget: bool,
+        should_remove: F,
+    ) -> JfResult<Removed>
+    where
+        F: Fn(&str, &CachedImage) -> bool,
+    {
+        let candidates: Vec<(String, String, CachedImage)> = self.modify(|file| {
+            let candidates = file
+                .namespaces
+                .iter()
+                .flat_map(|(namespace, images)| {
+                    images
+                        .iter()
+                        .filter(|(_, image)| should_remove(namespace, image))
+                        .map(|(key, image)| (namespace.clone(), key.clone(), image.clone()))
+                })
+                .collect();
+
+            (candidates, false)
+        })?;
+
+        let mut removed = Removed::default();
+        let mut removable = Vec::new();
+
+        for (namespace, key, image) in candidates {
+            if let Some(handle) = &image.delete_handle {
+                match hosts.iter().find(|host| host.name() == namespace) {
+                    Some(host) => {
+                        if let Err(err) = host.delete(handle) {
+                            warn!("Couldn't delete {} from {}: {}", image.url, namespace, err);
+                            removed.failed += 1;
+                            continue;
+                        }
+
+                        debug!("Deleted {} from {}", image.url, namespace);
+                        removed.deleted += 1;
+                    }
+                    None if forget => debug!("Forgetting {} on {}", image.url, namespace),
+                    None => {
+                        debug!(
+                            "Keeping {}, {} isn't available to delete it",
+                            image.url, namespace
+                        );
+                        removed.failed += 1;
+                        continue;
+                    }
+                }
+            }
+
+            removable.push((namespace, key, image));
+        }
+
+        self.modify(|file| {
+            for (namespace, key, image) in &removable {
+                let Some(images) = file.namespaces.get_mut(namespace) else {
+                    continue;
+                };
+
+                // Another process may have uploaded the image again in the meantime
+                if images.get(key) == Some(image) {
+                    debug!("Removing {} image of {}", namespace, key);
+                    images.remove(key);
+                    removed.removed += 1;
+                }
+            }
+
+            file.namespaces.retain(|_, images| !images.is_empty());
+
+            (removed, removed.removed > 0)
+        })
+    }
+
+    /// Writes the cache to a temporary file and moves it over the old one
+    fn write(&self, state: &mut State) -> JfResult<()> {
+        if let Some(parent) = self.location.parent() {
+            fs::create_dir_all(parent).map_err(JfError::Cache)?;
+        }
+
+        let mut temporary = self.location.clone().into_os_string();
+        temporary.push(".tmp");
+
+        let contents = serde_json::to_string_pretty(&state.file).map_err(cache_error)?;
+
+        let mut output = File::create(&temporary).map_err(JfError::Cache)?;
+        output
+            .write_all(contents.as_bytes())
+            .and_then(|_| output.sync_all())
+            .map_err(JfError::Cache)?;
+
+        fs::rename(&temporary, &self.location).map_err(JfError::Cache)?;
+        state.version = version(&self.location);
+
+        Ok(())
+    }
+}
+
+fn key(item_id: &str, image_tag: Option<&str>) -> String {
+    match image_tag {
+        Some(tag) => format!("{}:{}", item_id, tag),
+        None => item_id.to_string(),
+    }
+}
+
+/// Url of the first of `keys` that's in `images`
+fn find(
+    namespace: &str,
+    images: &BTreeMap<String, CachedImage>,
+    keys: &[String],
+) -> JfResult<Option<Url>> {
+    let Some(image) = keys.iter().find_map(|key| images.get(key)) else {
+        return Ok(None);
+    };
+
+    debug!("Found cached {} image: \"{}\"", namespace, image.url);
+
+    Ok(Some(Url::parse(&image.url)?))
+}
+
+/// Reads the cache at `location`, a missing file is an empty cache
+fn read(location: &Path) -> JfResult<State> {
+    let version = version(location);
+
+    let file = match fs::read_to_string(location) {
+        Ok(contents) => parse(&contents).map_err(cache_error)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => CacheFile {
+            version: SCHEMA_VERSION,
+            ..Default::default()
+        },
+        Err(err) => return Err(JfError::Cache(err)),
+    };
+
+    Ok(State { file, version })
+}
+
+fn version(location: &Path) -> Option<FileVersion> {
+    let metadata = fs::metadata(location).ok()?;
+
+    Some(FileVersion {
+        modified: metadata.modified().ok()?,
+        len: metadata.len(),
+        #[cfg(unix)]
+        inode: std::os::unix::fs::MetadataExt::ino(&metadata),
+    })
+}
+
+fn cache_error<E: Into<std::io::Error>>(err: E) -> JfError {
+    JfError::Cache(err.into())
+}
+
+fn parse(contents: &str) -> std::io::Result<CacheFile> {
+    let value: serde_json::Value = serde_json::from_str(contents)?;
+
+    if value.is_array() {
+        return Ok(migrate(serde_json::from_value(value)?));
+    }
+
+    let version = value["version"].as_u64().unwrap_or_default();
+    if version > SCHEMA_VERSION as u64 {
+        return Err(std::io::Error::other(format!(
+            "the image cache was written by a newer version of jellyfin-rpc (version {})",
+            version
+        )));
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Version 1 kept a list of images per host, imgur and litterbox usually shared the same file.
+/// The host is recognized from the url, litterbox images expire 72 hours after their upload.
+fn migrate(images: Vec<LegacyImage>) -> CacheFile {
+    info!("Migrating {} images to the new image cache", images.len());
+
+    let mut file = CacheFile {
+        version: SCHEMA_VERSION,
+        ..Default::default()
+    };
+
+    for image in images {
+        let is_litterbox = image.timestamp.is_some()
+            || Url::parse(&image.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|host| host.ends_with("catbox.moe")))
+                .unwrap_or(false);
+
+        let (namespace, cached) = if is_litterbox {
+            // Without a valid upload time there's no telling whether the image still exists
+            let Some(uploaded_at) = image.timestamp.and_then(|t| t.parse::<i64>().ok()) else {
+                debug!(
+                    "Dropping litterbox image of {} without a valid timestamp",
+                    image.id
+                );
+                continue;
+            };
+
+            let cached = CachedImage {
+                url: image.url,
+                uploaded_at,
+                expires_at: Some(uploaded_at + LEGACY_LITTERBOX_EXPIRY),
+                delete_handle: None,
+            };
+
+            ("litterbox", cached)
+        } else {
+            let cached = CachedImage {
+                url: image.url,
+                uploaded_at: 0,
+                expires_at: None,
+                delete_handle: None,
+            };
+
+            ("imgur", cached)
+        };
+
+        file.namespaces
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(image.id, cached);
+    }
+
+    file
+}
@@ -0,0 +1,170 @@
+use std::{
+    error::Error,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use log::{debug, warn};
+use ring::digest;
+use url::Url;
+
+use crate::{external::cache::ImageCache, JfError, JfResult};
+
+/// Somewhere images from Jellyfin can be uploaded to, so Discord can show them
+/// without the Jellyfin server being reachable from the internet.
+///
+/// Hosts are registered on the [`ClientBuilder`](crate::ClientBuilder) with
+/// [`image_host()`](crate::ClientBuilder::image_host) and tried in order until one of them succeeds.
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::{ImageHost, UploadedImage};
+/// use std::error::Error;
+///
+/// struct MyHost;
+///
+/// impl ImageHost for MyHost {
+///     fn name(&self) -> &'static str {
+///         "my-host"
+///     }
+///
+///     fn upload(&self, image: Vec<u8>) -> Result<UploadedImage, Box<dyn Error + Send + Sync>> {
+///         // Send `image` somewhere and return where it ended up
+///         Ok(UploadedImage {
+///             url: "https://images.example.com/1.png".parse()?,
+///             expires_at: None,
+///             delete_handle: None,
+///         })
+///     }
+/// }
+/// ```
+pub trait ImageHost: Send + Sync {
+    /// Used in logs and errors, e.g. `"imgur"`
+    fn name(&self) -> &'static str;
+
+    /// Uploads an image and returns where it can be found.
+    ///
+    /// This is blocking, the async client runs it on tokio's blocking thread pool.
+    fn upload(&self, image: Vec<u8>) -> Result<UploadedImage, Box<dyn Error + Send + Sync>>;
+
+    /// Removes an image using the `delete_handle` it was uploaded with.
+    ///
+    /// Does nothing by default.
+    fn delete(&self, _delete_handle: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// How long uploaded images can be used for, if the host doesn't say so when uploading.
+    ///
+    /// Images are kept forever by default.
+    fn ttl(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// An image that was uploaded to an [`ImageHost`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UploadedImage {
+    pub url: Url,
+    /// Unix timestamp of when the host removes the image, `None` if it's kept
+    pub expires_at: Option<i64>,
+    /// Needed by [`ImageHost::delete`] to remove the image again
+    pub delete_handle: Option<String>,
+}
+
+/// Looks through the caches of `hosts` for an image of `item_id` that hasn't expired
+pub(crate) fn find_cached(
+    hosts: &[Arc<dyn ImageHost>],
+    cache: Option<&ImageCache>,
+    item_id: &str,
+    image_tag: Option<&str>,
+) -> JfResult<Option<Url>> {
+    let Some(cache) = cache else {
+        return Ok(None);
+    };
+
+    for host in hosts {
+        if let Some(url) = cache.get(host.name(), item_id, image_tag)? {
+            return Ok(Some(url));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Uploads `image` to the first of `hosts` that accepts it and caches where it ended up
+pub(crate) fn upload(
+    hosts: &[Arc<dyn ImageHost>],
+    cache: Option<&ImageCache>,
+    item_id: &str,
+    image_tag: Option<&str>,
+    image: Vec<u8>,
+) -> JfResult<Url> {
+    let mut last_error = None;
+
+    for host in hosts {
+        debug!("Uploading image to {}", host.name());
+
+        let uploaded = match host.upload(image.clone()) {
+            Ok(uploaded) => uploaded,
+            Err(err) => {
+                warn!("Uploading image to {} failed: {}", host.name(), err);
+                last_error = Some(JfError::image_host(host.name(), err));
+                continue;
+            }
+        };
+
+        if let Some(cache) = cache {
+            if let Err(err) = cache.insert(host.name(), item_id, image_tag, &uploaded, host.ttl()) {
+                warn!("Couldn't cache the {} image: {}", host.name(), err);
+            }
+        }
+
+        return Ok(uploaded.url);
+    }
+
+    Err(last_error.unwrap_or(JfError::NoImage))
+}
+
+/// Blocking http client of an image host, kept for every upload so connections are reused.
+///
+/// It's built on first use as building it inside an async runtime panics,
+/// the async client only uploads from tokio's blocking thread pool.
+#[derive(Default)]
+pub(crate) struct HostClient(OnceLock<reqwest::blocking::Client>);
+
+impl HostClient {
+    pub fn get(&self) -> reqwest::Result<&reqwest::blocking::Client> {
+        if let Some(client) = self.0.get() {
+            return Ok(client);
+        }
+
+        let client = reqwest::blocking::Client::builder().build()?;
+        Ok(self.0.get_or_init(|| client))
+    }
+}
+
+/// Name for an uploaded image, the hash of its contents so the same image always gets the same name
+pub(crate) fn file_name(image: &[u8]) -> String {
+    let extension = image::guess_format(image)
+        .ok()
+        .and_then(|format| format.extensions_str().first().copied())
+        .unwrap_or("jpg");
+
+    format!("{}.{}", sha256_hex(image), extension)
+}
+
+/// Mime type of an image, `application/octet-stream` if it isn't recognized
+pub(crate) fn content_type(image: &[u8]) -> &'static str {
+    image::guess_format(image)
+        .map(|format| format.to_mime_type())
+        .unwrap_or("application/octet-stream")
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex(digest::digest(&digest::SHA256, data).as_ref())
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,158 @@
+use std::error::Error;
+
+use log::debug;
+use url::Url;
+
+use crate::external::host::{content_type, file_name, HostClient, ImageHost, UploadedImage};
+
+/// How [`HttpUploader`] sends the image.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum UploadMethod {
+    /// A multipart form with the image in a single file field
+    #[default]
+    Post,
+    /// The image as the request body
+    Put,
+}
+
+/// Where [`HttpUploader`] finds the url of an uploaded image.
+#[derive(Debug, Clone, PartialEq)]
+enum ResponseUrl {
+    /// The response body is the url
+    Body,
+    /// A string in the JSON response, found with a JSON pointer
+    Json(String),
+    /// Built from a template, the response is ignored
+    Template(String),
+}
+
+/// Uploads images to a self-hosted server such as Chevereto, Zipline or PictShare.
+///
+/// `{name}` in the upload url and public url is replaced with the name of the image,
+/// the hash of its contents followed by its extension.
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::{ClientBuilder, HttpUploader};
+///
+/// let mut zipline = HttpUploader::new("https://zipline.example.com/api/upload");
+/// zipline
+///     .header("Authorization", "my-token")
+///     .form_field("file")
+///     .url_from_json("/files/0/url");
+///
+/// let mut builder = ClientBuilder::new();
+/// builder.image_host(zipline);
+/// ```
+pub struct HttpUploader {
+    upload_url: String,
+    method: UploadMethod,
+    form_field: String,
+    headers: Vec<(String, String)>,
+    response_url: ResponseUrl,
+    client: HostClient,
+}
+
+impl HttpUploader {
+    /// Images are sent to `upload_url`, by default as a `POST` with the image in the `file` field
+    /// and the response body as the url of the uploaded image.
+    pub fn new<T: Into<String>>(upload_url: T) -> Self {
+        Self {
+            upload_url: upload_url.into(),
+            method: UploadMethod::default(),
+            form_field: String::from("file"),
+            headers: Vec::new(),
+            response_url: ResponseUrl::Body,
+            client: HostClient::default(),
+        }
+    }
+
+    /// How the image is sent.
+    ///
+    /// Defaults to [`UploadMethod::Post`].
+    pub fn method(&mut self, method: UploadMethod) -> &mut Self {
+        self.method = method;
+        self
+    }
+
+    /// Name of the form field the image is sent in when using [`UploadMethod::Post`].
+    ///
+    /// Defaults to `file`.
+    pub fn form_field<T: Into<String>>(&mut self, name: T) -> &mut Self {
+        self.form_field = name.into();
+        self
+    }
+
+    /// Header sent with every upload, usually the api key or token of the server.
+    pub fn header<T: Into<String>, Y: Into<String>>(&mut self, name: T, value: Y) -> &mut Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Read the url of the uploaded image from the JSON response,
+    /// `pointer` is a JSON pointer such as `/image/url`.
+    pub fn url_from_json<T: Into<String>>(&mut self, pointer: T) -> &mut Self {
+        self.response_url = ResponseUrl::Json(pointer.into());
+        self
+    }
+
+    /// Build the url of the uploaded image from a template instead of reading the response,
+    /// e.g. `https://images.example.com/{name}`.
+    pub fn public_url<T: Into<String>>(&mut self, template: T) -> &mut Self {
+        self.response_url = ResponseUrl::Template(template.into());
+        self
+    }
+}
+
+impl ImageHost for HttpUploader {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn upload(&self, image: Vec<u8>) -> Result<UploadedImage, Box<dyn Error + Send + Sync>> {
+        let name = file_name(&image);
+        let upload_url = Url::parse(&self.upload_url.replace("{name}", &name))?;
+        let content_type = content_type(&image);
+
+        let client = self.client.get()?;
+
+        let mut request = match self.method {
+            UploadMethod::Post => client.post(upload_url).multipart(
+                reqwest::blocking::multipart::Form::new().part(
+                    self.form_field.clone(),
+                    reqwest::blocking::multipart::Part::bytes(image)
+                        .file_name(name.clone())
+                        .mime_str(content_type)?,
+                ),
+            ),
+            UploadMethod::Put => client
+                .put(upload_url)
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(image),
+        };
+
+        for (header, value) in &self.headers {
+            request = request.header(header, value);
+        }
+
+        let res = request.send()?.error_for_status()?.text()?;
+
+        debug!("Response from {}: \"{}\"", self.upload_url, res);
+
+        let url = match &self.response_url {
+            ResponseUrl::Body => res.trim().to_string(),
+            ResponseUrl::Json(pointer) => serde_json::from_str::<serde_json::Value>(&res)?
+                .pointer(pointer)
+                .and_then(|url| url.as_str())
+                .ok_or(format!("the response doesn't have a url at {}", pointer))?
+                .to_string(),
+            ResponseUrl::Template(template) => template.replace("{name}", &name),
+        };
+
+        Ok(UploadedImage {
+            url: Url::parse(&url)?,
+            expires_at: None,
+            delete_handle: None,
+        })
+    }
+}
@@ -27,3 +27,12 @@ pub fn make_square_with_blur(input_bytes: &[u8]) -> Result<Vec<u8>, image::Image
     canvas.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png)?;
     Ok(buf)
 }
+
+/// Processes the image if the client is configured to do so
+pub fn prepare_image(process_images: bool, image_bytes: &[u8]) -> Result<Vec<u8>, image::ImageError> {
+    if process_images {
+        make_square_with_blur(image_bytes)
+    } else {
+        Ok(image_bytes.to_vec())
+    }
+}
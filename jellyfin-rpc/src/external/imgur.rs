@@ -1,28 +1,11 @@
-use std::{
-    fs::{self, File, OpenOptions},
-    io::{Error, ErrorKind, Write},
-    path::Path,
-};
+use std::error::Error;
 
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use url::Url;
 
-use crate::{Client, JfResult};
+use crate::external::host::{HostClient, ImageHost, UploadedImage};
 
-#[derive(Deserialize, Serialize)]
-struct ImageUrl {
-    id: String,
-    url: String,
-}
-
-impl ImageUrl {
-    fn new<T: Into<String>, Y: Into<String>>(id: T, url: Y) -> Self {
-        Self {
-            id: id.into(),
-            url: url.into(),
-        }
-    }
-}
+const IMGUR_API: &str = "https://api.imgur.com/3/image";
 
 #[derive(Deserialize)]
 struct ImgurResponse {
@@ -32,87 +15,60 @@ struct ImgurResponse {
 #[derive(Deserialize)]
 struct Data {
     link: String,
+    deletehash: Option<String>,
 }
 
-pub fn get_image(client: &Client) -> JfResult<Url> {
-    let mut image_urls = read_file(client)?;
-
-    if let Some(image_url) = image_urls
-        .iter()
-        .find(|image_url| client.session.as_ref().unwrap().item_id == image_url.id)
-    {
-        Ok(Url::parse(&image_url.url)?)
-    } else {
-        let imgur_url = upload(client)?;
-
-        let image_url = ImageUrl::new(
-            &client.session.as_ref().unwrap().item_id,
-            imgur_url.as_str(),
-        );
-
-        image_urls.push(image_url);
-
-        let mut file = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&client.imgur_options.urls_location)?;
-
-        file.write_all(serde_json::to_string(&image_urls)?.as_bytes())?;
-
-        let _ = file.flush();
-
-        Ok(imgur_url)
-    }
+/// Uploads images to imgur, they're kept until they are deleted.
+pub struct Imgur {
+    client_id: String,
+    client: HostClient,
 }
 
-fn read_file(client: &Client) -> JfResult<Vec<ImageUrl>> {
-    if let Ok(contents_raw) = fs::read_to_string(&client.imgur_options.urls_location) {
-        if let Ok(contents) = serde_json::from_str::<Vec<ImageUrl>>(&contents_raw) {
-            return Ok(contents);
+impl Imgur {
+    /// `client_id` is used to upload images through their API.
+    pub fn new<T: Into<String>>(client_id: T) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client: HostClient::default(),
         }
     }
 
-    let path = Path::new(&client.imgur_options.urls_location)
-        .parent()
-        .ok_or(Error::new(
-            ErrorKind::Other,
-            "Can't find parent folder of urls.json",
-        ))?;
-
-    fs::create_dir_all(path)?;
-
-    let mut file = File::create(client.imgur_options.urls_location.clone())?;
-
-    let new: Vec<ImageUrl> = vec![];
-
-    file.write_all(serde_json::to_string(&new)?.as_bytes())?;
-
-    let _ = file.flush();
-
-    Ok(new)
+    fn authorization(&self) -> String {
+        format!("Client-ID {}", self.client_id)
+    }
 }
 
-fn upload(client: &Client) -> JfResult<Url> {
-    let image_bytes = client.reqwest.get(client.get_image()?).send()?.bytes()?;
-
-    let imgur_client = reqwest::blocking::Client::builder().build()?;
+impl ImageHost for Imgur {
+    fn name(&self) -> &'static str {
+        "imgur"
+    }
 
-    let body = if client.process_images {
-        use crate::external::image_utils::make_square_with_blur;
-        make_square_with_blur(&image_bytes)?
-    } else {
-        image_bytes.to_vec()
-    };
+    fn upload(&self, image: Vec<u8>) -> Result<UploadedImage, Box<dyn Error + Send + Sync>> {
+        let res: ImgurResponse = self
+            .client
+            .get()?
+            .post(IMGUR_API)
+            .header(reqwest::header::AUTHORIZATION, self.authorization())
+            .body(image)
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(UploadedImage {
+            url: Url::parse(&res.data.link)?,
+            expires_at: None,
+            delete_handle: res.data.deletehash,
+        })
+    }
 
-    let res: ImgurResponse = imgur_client
-        .post("https://api.imgur.com/3/image")
-        .header(
-            reqwest::header::AUTHORIZATION,
-            format!("Client-ID {}", client.imgur_options.client_id),
-        )
-        .body(body)
-        .send()?
-        .json()?;
+    fn delete(&self, delete_handle: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.client
+            .get()?
+            .delete(format!("{}/{}", IMGUR_API, delete_handle))
+            .header(reqwest::header::AUTHORIZATION, self.authorization())
+            .send()?
+            .error_for_status()?;
 
-    Ok(Url::parse(res.data.link.as_str())?)
+        Ok(())
+    }
 }
@@ -1,3 +1,7 @@
+pub mod cache;
+pub mod host;
+pub mod http;
 pub mod imgur;
 pub mod litterbox;
 pub mod image_utils;
+pub mod s3;
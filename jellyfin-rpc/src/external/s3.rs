@@ -0,0 +1,252 @@
+use std::error::Error;
+
+use chrono::{DateTime, Utc};
+use ring::hmac;
+use url::Url;
+
+use crate::external::host::{
+    content_type, file_name, hex, sha256_hex, HostClient, ImageHost, UploadedImage,
+};
+
+/// Uploads images to an S3-compatible bucket, such as MinIO or Cloudflare R2.
+///
+/// Images are uploaded with a path-style request, `{endpoint}/{bucket}/{key}`,
+/// and have to be publicly readable at `{public url}/{key}`.
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::{ClientBuilder, S3Bucket};
+///
+/// let mut bucket = S3Bucket::new(
+///     "https://0123456789abcdef.r2.cloudflarestorage.com",
+///     "artwork",
+///     "access key id",
+///     "secret access key",
+///     "https://artwork.example.com",
+/// );
+/// bucket.region("auto").prefix("jellyfin-rpc/");
+///
+/// let mut builder = ClientBuilder::new();
+/// builder.image_host(bucket);
+/// ```
+pub struct S3Bucket {
+    endpoint: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+    public_url: String,
+    region: String,
+    prefix: String,
+    client: HostClient,
+}
+
+impl S3Bucket {
+    pub fn new<T, Y, U, I, O>(
+        endpoint: T,
+        bucket: Y,
+        access_key: U,
+        secret_key: I,
+        public_url: O,
+    ) -> Self
+    where
+        T: Into<String>,
+        Y: Into<String>,
+        U: Into<String>,
+        I: Into<String>,
+        O: Into<String>,
+    {
+        Self {
+            endpoint: endpoint.into(),
+            bucket: bucket.into(),
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            public_url: public_url.into(),
+            region: String::from("us-east-1"),
+            prefix: String::new(),
+            client: HostClient::default(),
+        }
+    }
+
+    /// Region the bucket is in, R2 uses `auto`.
+    ///
+    /// Defaults to `us-east-1`, which is also what MinIO uses unless configured otherwise.
+    pub fn region<T: Into<String>>(&mut self, region: T) -> &mut Self {
+        self.region = region.into();
+        self
+    }
+
+    /// Put in front of the name of every uploaded image, e.g. `jellyfin-rpc/`.
+    ///
+    /// Empty by default.
+    pub fn prefix<T: Into<String>>(&mut self, prefix: T) -> &mut Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Url an object is uploaded to
+    fn object_url(&self, key: &str) -> Result<Url, url::ParseError> {
+        Url::parse(&format!(
+            "{}/{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.bucket,
+            key
+        ))
+    }
+}
+
+impl ImageHost for S3Bucket {
+    fn name(&self) -> &'static str {
+        "s3"
+    }
+
+    fn upload(&self, image: Vec<u8>) -> Result<UploadedImage, Box<dyn Error + Send + Sync>> {
+        let key = self.prefix.clone() + &file_name(&image);
+        let url = self.object_url(&key)?;
+
+        let content_type = content_type(&image);
+        let payload_hash = sha256_hex(&image);
+        let now = Utc::now();
+
+        let headers = [
+            ("content-type", content_type.to_string()),
+            ("host", host(&url)),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+
+        let signature = sign(&SigningRequest {
+            method: "PUT",
+            url: &url,
+            headers: &headers,
+            payload_hash: &payload_hash,
+            access_key: &self.access_key,
+            secret_key: &self.secret_key,
+            region: &self.region,
+            time: now,
+        });
+
+        let mut request = self
+            .client
+            .get()?
+            .put(url)
+            .header(reqwest::header::AUTHORIZATION, signature)
+            .body(image);
+
+        // reqwest sets the host header itself
+        for (name, value) in headers.iter().filter(|(name, _)| *name != "host") {
+            request = request.header(*name, value);
+        }
+
+        request.send()?.error_for_status()?;
+
+        Ok(UploadedImage {
+            url: Url::parse(&format!(
+                "{}/{}",
+                self.public_url.trim_end_matches('/'),
+                key
+            ))?,
+            expires_at: None,
+            delete_handle: None,
+        })
+    }
+}
+
+/// Host header of a request, the port is only included if it isn't the default one
+fn host(url: &Url) -> String {
+    match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    }
+}
+
+/// Everything that goes into the signature of a request to S3
+pub(crate) struct SigningRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a Url,
+    /// Lowercase names, every one of them is signed
+    pub headers: &'a [(&'a str, String)],
+    pub payload_hash: &'a str,
+    pub access_key: &'a str,
+    pub secret_key: &'a str,
+    pub region: &'a str,
+    pub time: DateTime<Utc>,
+}
+
+/// Authorization header of a request, signed with AWS Signature Version 4
+pub(crate) fn sign(request: &SigningRequest) -> String {
+    let mut headers = request.headers.to_vec();
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(";");
+
+    let mut query: Vec<(String, String)> = request.url.query_pairs().into_owned().collect();
+    query.sort();
+    let canonical_query = query
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<String>>()
+        .join("&");
+
+    let canonical_request = [
+        request.method,
+        request.url.path(),
+        &canonical_query,
+        &canonical_headers,
+        &signed_headers,
+        request.payload_hash,
+    ]
+    .join("\n");
+
+    let date = request.time.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, request.region);
+
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        request.time.format("%Y%m%dT%H%M%SZ"),
+        scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let key = [request.region, "s3", "aws4_request"].iter().fold(
+        hmac_sha256(
+            format!("AWS4{}", request.secret_key).as_bytes(),
+            date.as_bytes(),
+        ),
+        |key, part| hmac_sha256(&key, part.as_bytes()),
+    );
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        request.access_key,
+        scope,
+        signed_headers,
+        hex(&hmac_sha256(&key, string_to_sign.as_bytes()))
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data)
+        .as_ref()
+        .to_vec()
+}
+
+/// Percent encodes everything except unreserved characters, as S3 expects in query strings
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,218 @@
+use crate::jellyfin::{MediaType, NameId, Session};
+use crate::JfError;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Types of images Jellyfin stores for an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageType {
+    Primary,
+    Thumb,
+    Backdrop,
+    Logo,
+    Art,
+    Banner,
+}
+
+impl std::fmt::Display for ImageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let res = match self {
+            ImageType::Primary => "Primary",
+            ImageType::Thumb => "Thumb",
+            ImageType::Backdrop => "Backdrop",
+            ImageType::Logo => "Logo",
+            ImageType::Art => "Art",
+            ImageType::Banner => "Banner",
+        };
+        write!(f, "{}", res)
+    }
+}
+
+/// Which item an image is taken from, relative to the item that is playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageItem {
+    /// The item that is playing
+    Item,
+    /// Season of an episode
+    Season,
+    /// Show of an episode or season
+    Series,
+    /// Album of a track
+    Album,
+    /// First album artist of a track or album
+    AlbumArtist,
+    /// First artist of a track or music video
+    Artist,
+}
+
+/// An image to show on the activity, see [`ClientBuilder::image_sources`](crate::ClientBuilder::image_sources).
+///
+/// Written as `type` for the item itself or `item:type`, e.g. `thumb`, `series:primary` or `album-artist:backdrop`.
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::{ImageItem, ImageSource, ImageType};
+///
+/// let source: ImageSource = "season:primary".parse().unwrap();
+/// assert_eq!(source, ImageSource::new(ImageItem::Season, ImageType::Primary));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ImageSource {
+    pub item: ImageItem,
+    pub image_type: ImageType,
+}
+
+impl ImageSource {
+    pub fn new(item: ImageItem, image_type: ImageType) -> Self {
+        Self { item, image_type }
+    }
+
+    /// The images used before sources could be configured,
+    /// the album cover for music and the show poster for episodes.
+    pub fn defaults(media_type: MediaType) -> Vec<Self> {
+        let primary = |item| Self::new(item, ImageType::Primary);
+
+        match media_type {
+            MediaType::Music => vec![primary(ImageItem::Album), primary(ImageItem::Item)],
+            MediaType::Episode => vec![primary(ImageItem::Series), primary(ImageItem::Item)],
+            _ => vec![primary(ImageItem::Item)],
+        }
+    }
+
+    /// Where to find the image for `session`, `None` if the item doesn't have it
+    pub(crate) fn resolve(&self, session: &Session) -> Option<JellyfinImage> {
+        let item = &session.now_playing_item;
+        let first_id = |items: &Option<Vec<NameId>>| Some(items.as_ref()?.first()?.id.clone());
+
+        let (item_id, tag) = match self.item {
+            ImageItem::Item => {
+                let tags = match self.image_type {
+                    ImageType::Backdrop => item
+                        .backdrop_image_tags
+                        .as_ref()
+                        .map(|tags| tags.first().cloned()),
+                    image_type => item
+                        .image_tags
+                        .as_ref()
+                        .map(|tags| tags.get(&image_type.to_string()).cloned()),
+                };
+
+                // When the tags are sent every image of the item is listed,
+                // so there's no point in asking for one that isn't
+                if tags == Some(None) {
+                    return None;
+                }
+
+                (item.id.clone(), tags.flatten())
+            }
+            ImageItem::Season => (item.season_id.clone()?, None),
+            ImageItem::Series => {
+                let tag = match self.image_type {
+                    ImageType::Primary => item.series_primary_image_tag.clone(),
+                    ImageType::Thumb => item.series_thumb_image_tag.clone(),
+                    _ => None,
+                };
+                (item.series_id.clone()?, tag)
+            }
+            ImageItem::Album => {
+                let tag = match self.image_type {
+                    ImageType::Primary => item.album_primary_image_tag.clone(),
+                    _ => None,
+                };
+                (item.album_id.clone()?, tag)
+            }
+            ImageItem::AlbumArtist => (first_id(&item.album_artists)?, None),
+            ImageItem::Artist => (first_id(&item.artist_items)?, None),
+        };
+
+        Some(JellyfinImage {
+            item_id,
+            image_type: self.image_type,
+            tag,
+        })
+    }
+}
+
+impl FromStr for ImageSource {
+    type Err = JfError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || JfError::InvalidImageSource(value.to_string());
+
+        let (item, image_type) = match value.to_lowercase().split_once(':') {
+            Some((item, image_type)) => {
+                let item = match item {
+                    "item" => ImageItem::Item,
+                    "season" => ImageItem::Season,
+                    "series" | "show" => ImageItem::Series,
+                    "album" => ImageItem::Album,
+                    "album-artist" | "albumartist" => ImageItem::AlbumArtist,
+                    "artist" => ImageItem::Artist,
+                    _ => return Err(invalid()),
+                };
+                (item, image_type.to_string())
+            }
+            None => (ImageItem::Item, value.to_lowercase()),
+        };
+
+        let image_type = match image_type.as_str() {
+            "primary" => ImageType::Primary,
+            "thumb" => ImageType::Thumb,
+            "backdrop" => ImageType::Backdrop,
+            "logo" => ImageType::Logo,
+            "art" => ImageType::Art,
+            "banner" => ImageType::Banner,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self::new(item, image_type))
+    }
+}
+
+impl TryFrom<String> for ImageSource {
+    type Error = JfError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ImageSource> for String {
+    fn from(source: ImageSource) -> Self {
+        let item = match source.item {
+            ImageItem::Item => "item",
+            ImageItem::Season => "season",
+            ImageItem::Series => "series",
+            ImageItem::Album => "album",
+            ImageItem::AlbumArtist => "album-artist",
+            ImageItem::Artist => "artist",
+        };
+
+        format!("{}:{}", item, source.image_type.to_string().to_lowercase())
+    }
+}
+
+/// An image on Jellyfin that may be shown on the activity.
+pub(crate) struct JellyfinImage {
+    pub item_id: String,
+    pub image_type: ImageType,
+    /// `None` if it isn't known whether the item has this image
+    pub tag: Option<String>,
+}
+
+impl JellyfinImage {
+    /// Path of the image relative to the server url
+    pub fn path(&self) -> String {
+        format!("Items/{}/Images/{}", self.item_id, self.image_type)
+    }
+
+    /// What the image is stored as in the image cache,
+    /// primary images only use the item id so older caches keep working
+    pub fn cache_id(&self) -> String {
+        match self.image_type {
+            ImageType::Primary => self.item_id.clone(),
+            image_type => format!("{}/{}", self.item_id, image_type),
+        }
+    }
+}
@@ -1,38 +1,57 @@
-use serde::{de::Visitor, Deserialize, Serialize};
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 use std::time::{SystemTime, SystemTimeError, UNIX_EPOCH};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct RawSession {
+    pub id: Option<String>,
+    pub user_id: Option<String>,
     pub user_name: Option<String>,
+    pub client: Option<String>,
+    pub device_name: Option<String>,
+    pub device_id: Option<String>,
+    pub remote_end_point: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_item")]
     pub now_playing_item: Option<NowPlayingItem>,
     pub play_state: Option<PlayState>,
 }
 
-impl RawSession {
-    pub fn build(self) -> Session {
-        //TODO: Figure out how to avoid this clone
-        let now_playing_item = self.now_playing_item.clone().unwrap();
+/// Deserializes the item through a [serde_json::Value], which is kept for `{raw:Field}` placeholders
+fn deserialize_item<'de, D>(deserializer: D) -> Result<Option<NowPlayingItem>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Some(raw) = Option::<serde_json::Value>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
 
-        let id = match now_playing_item.media_type {
-            MediaType::Episode => now_playing_item.series_id.unwrap_or(now_playing_item.id),
-            MediaType::Music => now_playing_item.album_id.unwrap_or(now_playing_item.id),
-            _ => now_playing_item.id,
-        };
+    let mut item = NowPlayingItem::deserialize(&raw).map_err(serde::de::Error::custom)?;
+    item.raw = raw;
 
+    Ok(Some(item))
+}
+
+impl RawSession {
+    pub fn build(self) -> Session {
         Session {
+            id: self.id,
             now_playing_item: self.now_playing_item.unwrap(),
             play_state: self.play_state.unwrap(),
-            item_id: id.to_string(),
+            client: self.client,
+            device_name: self.device_name,
         }
     }
 }
 
 #[derive(Debug)]
 pub struct Session {
+    pub id: Option<String>,
     pub now_playing_item: NowPlayingItem,
     pub play_state: PlayState,
-    pub item_id: String,
+    pub client: Option<String>,
+    pub device_name: Option<String>,
 }
 
 impl Session {
@@ -60,10 +79,117 @@ impl Session {
         artists
     }
 
+    pub fn is_video(&self) -> bool {
+        matches!(
+            self.now_playing_item.media_type,
+            MediaType::Movie
+                | MediaType::Episode
+                | MediaType::LiveTv
+                | MediaType::MusicVideo
+                | MediaType::Video
+                | MediaType::Trailer
+                | MediaType::Recording
+                | MediaType::ChannelItem
+        )
+    }
+
+    /// Reading progress of a book, as far as the reader reports it.
+    ///
+    /// Readers report their position as the playback position, the EPUB reader of jellyfin-web
+    /// uses thousandths of the book while its PDF and comic readers use the index of the page.
+    pub fn book_progress(&self) -> BookProgress {
+        let item = &self.now_playing_item;
+        let Some(ticks) = self.play_state.position_ticks else {
+            return BookProgress::default();
+        };
+        let position = ticks / 10000;
+
+        let chapter = item
+            .chapters
+            .iter()
+            .flatten()
+            .filter(|chapter| chapter.start_position_ticks <= ticks)
+            .max_by_key(|chapter| chapter.start_position_ticks)
+            .and_then(|chapter| chapter.name.clone());
+
+        if self.is_book_format("epub") {
+            return BookProgress {
+                percent: Some((position / 10).clamp(0, 100)),
+                chapter,
+                ..Default::default()
+            };
+        }
+
+        // Jellyfin doesn't know how many pages a book has, unless the reader reported it as the runtime
+        let page = position + 1;
+        let page_count = item
+            .run_time_ticks
+            .map(|ticks| ticks / 10000)
+            .filter(|&count| count > 0);
+
+        BookProgress {
+            page: Some(page),
+            page_count,
+            percent: page_count.map(|count| (page * 100 / count).clamp(0, 100)),
+            chapter,
+        }
+    }
+
+    /// Whether the book is stored as `format`, jellyfin-web picks the reader by the file extension
+    /// as the container isn't always set
+    fn is_book_format(&self, format: &str) -> bool {
+        let item = &self.now_playing_item;
+        let extension = item
+            .path
+            .as_deref()
+            .and_then(|path| Path::new(path).extension())
+            .and_then(|extension| extension.to_str());
+
+        extension.is_some_and(|extension| extension.eq_ignore_ascii_case(format))
+            || item.container.as_deref().is_some_and(|container| {
+                container.split(',').any(|c| c.eq_ignore_ascii_case(format))
+            })
+    }
+
+    /// Authors of a book, the artists are used if the people aren't known
+    pub fn authors(&self) -> Vec<String> {
+        let item = &self.now_playing_item;
+        let authors: Vec<String> = item
+            .people
+            .iter()
+            .flatten()
+            .filter(|person| person.kind.as_deref() == Some("Author"))
+            .map(|person| person.name.clone())
+            .collect();
+
+        if authors.is_empty() {
+            item.artists.clone().unwrap_or_default()
+        } else {
+            authors
+        }
+    }
+
+    pub fn is_audio(&self) -> bool {
+        matches!(
+            self.now_playing_item.media_type,
+            MediaType::Music | MediaType::AudioBook
+        )
+    }
+
     pub fn get_time(&self) -> Result<PlayTime, SystemTimeError> {
         match self.now_playing_item.media_type {
             MediaType::Book => return Ok(PlayTime::None),
-            MediaType::LiveTv => return Ok(PlayTime::None),
+            MediaType::Photo => return Ok(PlayTime::None),
+            // Live TV can't be seeked through so the timestamps follow the programme
+            MediaType::LiveTv => {
+                return Ok(match &self.now_playing_item.current_program {
+                    _ if self.play_state.is_paused => PlayTime::Paused,
+                    Some(program) => program
+                        .times()
+                        .map_or(PlayTime::None, |(start, end)| PlayTime::Some(start, end)),
+                    None => PlayTime::None,
+                })
+            }
             _ => {}
         }
 
@@ -93,6 +219,16 @@ impl Session {
     }
 }
 
+/// Reading progress of a book, see [`Session::book_progress`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BookProgress {
+    pub page: Option<i64>,
+    pub page_count: Option<i64>,
+    pub percent: Option<i64>,
+    /// Name of the chapter that is being read, if the item has chapters
+    pub chapter: Option<String>,
+}
+
 #[derive(PartialEq)]
 pub enum PlayTime {
     Some(i64, i64),
@@ -155,12 +291,22 @@ pub struct NowPlayingItem {
     pub id: String,
     pub run_time_ticks: Option<i64>,
     pub production_year: Option<i64>,
+    /// e.g. `2008-01-20T00:00:00.0000000Z`
+    pub premiere_date: Option<String>,
     pub genres: Option<Vec<String>>,
     pub external_urls: Option<Vec<ExternalUrl>>,
     pub critic_rating: Option<i64>,
     pub community_rating: Option<f64>,
     pub original_title: Option<String>,
     pub path: Option<String>,
+    /// Tag of every image of the item, changes when the image does
+    pub image_tags: Option<HashMap<String, String>>,
+    pub backdrop_image_tags: Option<Vec<String>>,
+    pub people: Option<Vec<Person>>,
+    // Book related
+    /// File format, e.g. `"epub"` or `"pdf"`
+    pub container: Option<String>,
+    pub chapters: Option<Vec<Chapter>>,
     // Episode related
     pub parent_index_number: Option<i32>,
     pub index_number: Option<i32>,
@@ -168,11 +314,87 @@ pub struct NowPlayingItem {
     pub series_name: Option<String>,
     pub series_id: Option<String>,
     pub series_studio: Option<String>,
+    pub series_primary_image_tag: Option<String>,
+    pub series_thumb_image_tag: Option<String>,
+    pub season_id: Option<String>,
     // Audio related
     pub artists: Option<Vec<String>>,
     pub extra_type: Option<String>,
     pub album_id: Option<String>,
     pub album: Option<String>,
+    pub album_primary_image_tag: Option<String>,
+    pub album_artists: Option<Vec<NameId>>,
+    pub artist_items: Option<Vec<NameId>>,
+    // Live TV related
+    /// Channel number, e.g. `"101"`
+    pub number: Option<String>,
+    /// Programme airing on the channel, looked up separately if the session doesn't include it
+    pub current_program: Option<Program>,
+    /// The item as Jellyfin sent it, `{raw:Field}` placeholders are looked up in it
+    #[serde(skip)]
+    pub raw: serde_json::Value,
+}
+
+/// Name and id of a related item, such as an artist.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct NameId {
+    pub name: String,
+    pub id: String,
+}
+
+/// Someone credited on an item.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Person {
+    pub name: String,
+    /// e.g. `"Author"` or `"Actor"`
+    #[serde(rename = "Type")]
+    pub kind: Option<String>,
+}
+
+/// A chapter of an item, starting at the given position.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Chapter {
+    pub name: Option<String>,
+    pub start_position_ticks: i64,
+}
+
+/// A programme on a Live TV channel.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Program {
+    pub name: String,
+    pub episode_title: Option<String>,
+    pub channel_number: Option<String>,
+    /// e.g. `2024-05-01T18:00:00.0000000Z`
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+}
+
+impl Program {
+    /// Start and end as unix timestamps
+    pub fn times(&self) -> Option<(i64, i64)> {
+        Some((
+            timestamp(self.start_date.as_deref()?)?,
+            timestamp(self.end_date.as_deref()?)?,
+        ))
+    }
+
+    pub fn has_ended(&self, now: i64) -> bool {
+        self.times().is_some_and(|(_, end)| end <= now)
+    }
+}
+
+fn timestamp(date: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(date)
+        .map(|date| date.timestamp())
+        .or_else(|_| {
+            chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|date| date.and_utc().timestamp())
+        })
+        .ok()
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -183,7 +405,7 @@ pub struct ExternalUrl {
 }
 
 /// The type of the currently playing content.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
 pub enum MediaType {
     /// If the content playing is a Movie.
     Movie,
@@ -197,7 +419,20 @@ pub enum MediaType {
     Book,
     /// If the content playing is an Audio Book.
     AudioBook,
-    /// If the content is unrecognized.
+    /// If the content playing is a Music Video.
+    MusicVideo,
+    /// If the content playing is a home video or any other video outside of a movie or show library.
+    Video,
+    /// If the content playing is a Trailer.
+    Trailer,
+    /// If the content playing is a Live TV Recording.
+    Recording,
+    /// If the content being viewed is a Photo.
+    Photo,
+    /// If the content playing comes from a channel plugin.
+    ChannelItem,
+    /// If the content is unrecognized, it's displayed with a generic format.
+    #[default]
     None,
 }
 
@@ -214,6 +449,16 @@ impl Serialize for MediaType {
             MediaType::Book => serializer.serialize_unit_variant("MediaType", 4, "Book"),
             MediaType::AudioBook => serializer.serialize_unit_variant("MediaType", 4, "AudioBook"),
             MediaType::None => serializer.serialize_unit_variant("MediaType", 5, "None"),
+            MediaType::MusicVideo => {
+                serializer.serialize_unit_variant("MediaType", 6, "MusicVideo")
+            }
+            MediaType::Video => serializer.serialize_unit_variant("MediaType", 7, "Video"),
+            MediaType::Trailer => serializer.serialize_unit_variant("MediaType", 8, "Trailer"),
+            MediaType::Recording => serializer.serialize_unit_variant("MediaType", 9, "Recording"),
+            MediaType::Photo => serializer.serialize_unit_variant("MediaType", 10, "Photo"),
+            MediaType::ChannelItem => {
+                serializer.serialize_unit_variant("MediaType", 11, "ChannelItem")
+            }
         }
     }
 }
@@ -267,18 +512,18 @@ impl std::fmt::Display for MediaType {
             MediaType::Music => "Music",
             MediaType::Book => "Book",
             MediaType::AudioBook => "AudioBook",
+            MediaType::MusicVideo => "MusicVideo",
+            MediaType::Video => "Video",
+            MediaType::Trailer => "Trailer",
+            MediaType::Recording => "Recording",
+            MediaType::Photo => "Photo",
+            MediaType::ChannelItem => "ChannelItem",
             MediaType::None => "None",
         };
         write!(f, "{}", res)
     }
 }
 
-impl Default for MediaType {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl From<&'static str> for MediaType {
     fn from(value: &'static str) -> Self {
         match value {
@@ -288,6 +533,12 @@ impl From<&'static str> for MediaType {
             "livetv" | "tvchannel" => Self::LiveTv,
             "book" => Self::Book,
             "audiobook" => Self::AudioBook,
+            "musicvideo" => Self::MusicVideo,
+            "video" => Self::Video,
+            "trailer" => Self::Trailer,
+            "recording" => Self::Recording,
+            "photo" => Self::Photo,
+            "channelitem" | "channelvideoitem" | "channelaudioitem" => Self::ChannelItem,
             _ => Self::None,
         }
     }
@@ -302,18 +553,74 @@ impl From<String> for MediaType {
             "livetv" | "tvchannel" => Self::LiveTv,
             "book" => Self::Book,
             "audiobook" => Self::AudioBook,
+            "musicvideo" => Self::MusicVideo,
+            "video" => Self::Video,
+            "trailer" => Self::Trailer,
+            "recording" => Self::Recording,
+            "photo" => Self::Photo,
+            "channelitem" | "channelvideoitem" | "channelaudioitem" => Self::ChannelItem,
             _ => Self::None,
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct PlayState {
     pub is_paused: bool,
     pub position_ticks: Option<i64>,
 }
 
+/// A list returned by the server, Emby wraps most of them in a query result.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    Plain(Vec<T>),
+    QueryResult {
+        #[serde(rename = "Items")]
+        items: Vec<T>,
+    },
+}
+
+impl<T> From<Listing<T>> for Vec<T> {
+    fn from(listing: Listing<T>) -> Self {
+        match listing {
+            Listing::Plain(items) => items,
+            Listing::QueryResult { items } => items,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct User {
+    pub name: String,
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct SystemInfo {
+    pub server_name: Option<String>,
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct AuthenticationResult {
+    pub user: User,
+    pub access_token: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct QuickConnectState {
+    pub authenticated: bool,
+    pub secret: String,
+    pub code: String,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "PascalCase")]
 pub struct VirtualFolder {
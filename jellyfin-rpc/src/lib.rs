@@ -1,3 +1,6 @@
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+pub use auth::{Credentials, Device, Login, QuickConnect};
 use discord_rich_presence::activity::{
     ActivityType, Button as ActButton, StatusDisplayType as DiscordIpcStatusDisplayType,
 };
@@ -6,43 +9,67 @@ use discord_rich_presence::{
     DiscordIpc, DiscordIpcClient,
 };
 pub use error::JfError;
+pub use external::cache::{CacheEntry, CacheStats, CachedImage, ImageCache, Removed};
+use external::host;
+pub use external::host::{ImageHost, UploadedImage};
+pub use external::http::{HttpUploader, UploadMethod};
+use external::image_utils::prepare_image;
+pub use external::imgur::Imgur;
+pub use external::litterbox::Litterbox;
+pub use external::s3::S3Bucket;
+use image::JellyfinImage;
+pub use image::{ImageItem, ImageSource, ImageType};
 pub use jellyfin::{Button, MediaType};
-use jellyfin::{ExternalUrl, NowPlayingItem, PlayTime, RawSession, Session, VirtualFolder};
+use jellyfin::{
+    ExternalUrl, Listing, NowPlayingItem, PlayTime, Program, RawSession, Session, SystemInfo, User,
+    VirtualFolder,
+};
+use local::LocalMachine;
 use log::{debug, warn};
-use reqwest::header::{HeaderMap, AUTHORIZATION};
+pub use placeholder::{placeholders, Placeholder};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use server::{Connection, Server};
+pub use server::{ServerConfig, ServerKind};
+use socket::UpdateSignal;
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::str::FromStr;
-use std::time::SystemTime;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use template::{Template, Values};
 use url::Url;
+pub use verify::{Problem, ServerReport, VerifyReport};
 
+#[cfg(feature = "async")]
+mod async_client;
+mod auth;
 mod error;
 mod external;
+mod image;
 mod jellyfin;
+mod local;
+mod placeholder;
+mod server;
+mod socket;
+mod template;
 #[cfg(test)]
 mod tests;
+mod verify;
 
-pub(crate) type JfResult<T> = Result<T, Box<dyn std::error::Error>>;
+pub(crate) type JfResult<T> = Result<T, JfError>;
 
 pub const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
 
+const PAUSED_IMAGE_URL: &str = "https://i.imgur.com/wlHSvYy.png";
+
 /// Client used to interact with jellyfin and discord
 pub struct Client {
     discord_ipc_client: DiscordIpcClient,
-    url: Url,
-    usernames: Vec<String>,
-    reqwest: reqwest::blocking::Client,
-    session: Option<Session>,
-    buttons: Option<Vec<Button>>,
-    music_display_options: DisplayOptions,
-    movies_display_options: DisplayOptions,
-    episodes_display_options: DisplayOptions,
-    blacklist: Blacklist,
-    show_paused: bool,
-    show_images: bool,
-    imgur_options: ImgurOptions,
-    litterbox_options: LitterboxOptions,
-    process_images: bool,
-    large_image_text: String,
+    /// One for every server in `core.servers`
+    connections: Vec<Connection<reqwest::blocking::Client>>,
+    update_signal: Arc<UpdateSignal>,
+    core: Core,
 }
 
 impl Client {
@@ -54,12 +81,15 @@ impl Client {
     /// Connects to the discord socket
     pub fn connect(&mut self) -> JfResult<()> {
         self.discord_ipc_client.connect()?;
+        // A new Discord client doesn't have the activity yet
+        self.core.last_presence = None;
         Ok(())
     }
 
     /// Reconnects to the discord socket
     pub fn reconnect(&mut self) -> JfResult<()> {
         self.discord_ipc_client.reconnect()?;
+        self.core.last_presence = None;
         Ok(())
     }
 
@@ -84,14 +114,18 @@ impl Client {
     /// ```
     pub fn clear_activity(&mut self) -> JfResult<()> {
         self.discord_ipc_client.clear_activity()?;
+        self.core.last_presence = None;
         Ok(())
     }
 
     /// Gathers information from jellyfin about what is being played and displays it according to the options supplied to the builder.
     ///
+    /// Returns what happened to the activity, if nothing should be displayed anymore
+    /// the activity is cleared and [`ActivityOutcome::Cleared`] is returned.
+    ///
     /// # Example
     /// ```no_run
-    /// use jellyfin_rpc::Client;
+    /// use jellyfin_rpc::{ActivityOutcome, Client};
     ///
     /// let mut builder = Client::builder();
     /// builder.api_key("abcd1234")
@@ -102,169 +136,460 @@ impl Client {
     ///
     /// client.connect().unwrap();
     ///
-    /// client.set_activity().unwrap();
+    /// match client.set_activity().unwrap() {
+    ///     ActivityOutcome::Updated(presence) => println!("{}", presence.summary()),
+    ///     ActivityOutcome::Cleared => println!("Cleared activity"),
+    ///     _ => (),
+    /// }
     /// ```
-    pub fn set_activity(&mut self) -> JfResult<String> {
-        self.get_session()?;
+    pub fn set_activity(&mut self) -> JfResult<ActivityOutcome> {
+        match self.build_presence()? {
+            ActivityOutcome::Updated(presence) => {
+                // Discord only allows a few updates every 20 seconds, so unchanged presences aren't sent again
+                let outcome = self.core.presence_shown(presence);
+
+                if let ActivityOutcome::Updated(presence) = &outcome {
+                    if let Err(err) = self.discord_ipc_client.set_activity(presence.to_activity()) {
+                        self.core.last_presence = None;
+                        return Err(err.into());
+                    }
+                }
 
-        // Make sure the blacklist cache is loaded/valid
-        match &self.blacklist.libraries {
-            BlacklistedLibraries::Uninitialized => {
-                self.reload_blacklist();
+                Ok(outcome)
             }
-            BlacklistedLibraries::Initialized(_, init_time) => {
-                if SystemTime::now()
-                    .duration_since(*init_time)
-                    .map(|passed| passed.as_secs() > 3600)
-                    .unwrap_or(false)
-                {
-                    debug!("reloading blacklist after cache expiration");
-                    self.reload_blacklist();
+            outcome => {
+                if self.core.last_presence.take().is_some() {
+                    self.discord_ipc_client.clear_activity()?;
+                    return Ok(ActivityOutcome::Cleared);
                 }
+
+                Ok(outcome)
             }
         }
+    }
 
-        if let Some(session) = &self.session {
-            if session.now_playing_item.media_type == MediaType::None {
-                return Err(Box::new(JfError::UnrecognizedMediaType));
+    /// Gathers information from jellyfin about what is being played without touching Discord.
+    ///
+    /// Returns [`ActivityOutcome::Updated`] with the presence that `set_activity` would display,
+    /// or the reason why nothing would be displayed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use jellyfin_rpc::{ActivityOutcome, Client};
+    ///
+    /// let mut builder = Client::builder();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let mut client = builder.build().unwrap();
+    ///
+    /// if let ActivityOutcome::Updated(presence) = client.build_presence().unwrap() {
+    ///     println!("{}", presence.details);
+    /// }
+    /// ```
+    pub fn build_presence(&mut self) -> JfResult<ActivityOutcome> {
+        // Make sure the blacklist caches are loaded/valid, blacklisted sessions are passed over
+        for server in 0..self.core.servers.len() {
+            if self.core.servers[server].blacklist_expired() {
+                self.reload_server_blacklist(server);
             }
+        }
 
-            if self.check_blacklist()? {
-                return Err(Box::new(JfError::ContentBlacklist));
-            }
+        self.get_session()?;
 
-            let mut activity = Activity::new();
+        if let Some(outcome) = self.core.check_session()? {
+            return Ok(outcome);
+        }
 
-            let mut image_url = Url::from_str("https://i.imgur.com/oX6vcds.png")?;
+        let image_url = self.get_image_url()?;
 
-            if session.now_playing_item.media_type == MediaType::LiveTv {
-                image_url = Url::from_str("https://i.imgur.com/XxdHOqm.png")?;
-            } else if self.imgur_options.enabled && self.show_images {
-                if let Ok(imgur_url) = external::imgur::get_image(self) {
-                    image_url = imgur_url;
-                } else {
-                    debug!("imgur::get_image() didnt return an image, using default..")
-                }
-            } else if self.litterbox_options.enabled && self.show_images {
-                if let Ok(litterbox_url) = external::litterbox::get_image(self) {
-                    image_url = litterbox_url;
-                } else {
-                    debug!("litterbox::get_image() didn't return an image, using default..")
-                }
-            } else if self.show_images {
-                if let Ok(iu) = self.get_image() {
-                    image_url = iu;
-                } else {
-                    debug!("self.get_image() didnt return an image, using default..")
-                }
-            }
+        Ok(ActivityOutcome::Updated(Box::new(
+            self.core.build_presence(image_url)?,
+        )))
+    }
 
-            let mut assets = Assets::new().large_image(image_url.as_str());
+    /// Checks that every server can be reached, accepts the credentials and knows the configured users.
+    ///
+    /// Without this a wrong api key or a misspelled username just means no session is ever found.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use jellyfin_rpc::Client;
+    ///
+    /// let mut builder = Client::builder();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let client = builder.build().unwrap();
+    ///
+    /// let report = client.verify();
+    ///
+    /// for server in &report.servers {
+    ///     for problem in &server.problems {
+    ///         println!("{}: {}", server.url, problem);
+    ///     }
+    /// }
+    ///
+    /// if report.is_fatal() {
+    ///     std::process::exit(1);
+    /// }
+    /// ```
+    pub fn verify(&self) -> VerifyReport {
+        let servers = (0..self.core.servers.len())
+            .map(|server| {
+                let info = self.fetch_system_info(server);
+                let users = match info {
+                    Ok(_) => self.fetch_users(server),
+                    Err(_) => Ok(Vec::new()),
+                };
+
+                self.core.servers[server].report(info, users)
+            })
+            .collect();
 
-            if !self.large_image_text.is_empty() {
-                assets = assets.large_text(&self.large_image_text);
-            }
+        VerifyReport { servers }
+    }
 
-            let mut timestamps = Timestamps::new();
+    /// Blocks until Jellyfin pushes a session update over the WebSocket or `timeout` passes.
+    ///
+    /// Returns `true` if woken up by an update, when the WebSocket is disabled or
+    /// disconnected this simply sleeps for `timeout` and returns `false`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use jellyfin_rpc::Client;
+    /// use std::time::Duration;
+    ///
+    /// let mut builder = Client::builder();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let mut client = builder.build().unwrap();
+    ///
+    /// client.connect().unwrap();
+    ///
+    /// loop {
+    ///     client.wait_for_update(Duration::from_secs(7));
+    ///     client.set_activity().unwrap();
+    /// }
+    /// ```
+    pub fn wait_for_update(&mut self, timeout: Duration) -> bool {
+        let mut subscribed = false;
+
+        for (connection, server) in self.connections.iter_mut().zip(&self.core.servers) {
+            subscribed |= connection
+                .session_socket(server, &self.update_signal)
+                .is_some();
+        }
 
-            match session.get_time()? {
-                PlayTime::Some(start, end) => timestamps = timestamps.start(start).end(end),
-                PlayTime::None => (),
-                PlayTime::Paused if self.show_paused => {
-                    assets = assets
-                        .small_image("https://i.imgur.com/wlHSvYy.png")
-                        .small_text("Paused");
+        if subscribed {
+            self.update_signal.wait(timeout)
+        } else {
+            std::thread::sleep(timeout);
+            false
+        }
+    }
+
+    fn get_session(&mut self) -> JfResult<()> {
+        let mut sessions = Vec::new();
+        let mut errors = Vec::new();
+
+        for server in 0..self.core.servers.len() {
+            match self.get_server_sessions(server) {
+                Ok(server_sessions) => sessions.push(server_sessions),
+                Err(err) => {
+                    sessions.push(Vec::new());
+                    errors.push((server, err));
                 }
-                PlayTime::Paused => return Ok(String::new()),
             }
+        }
 
-            let buttons: Vec<Button>;
+        // Only give up if none of the servers could be reached
+        if errors.len() == self.core.servers.len() {
+            let (_, err) = errors.swap_remove(0);
+            return Err(err);
+        }
 
-            if let Some(b) = self.get_buttons() {
-                // This gets around the value being dropped immediately at the end of this if statement
-                buttons = b;
-                activity = activity.buttons(
-                    buttons
-                        .iter()
-                        .map(|b| ActButton::new(&b.name, &b.url))
-                        .collect(),
+        for (server, err) in errors {
+            warn!(
+                "Failed to get sessions from {}: {}",
+                self.core.servers[server].url, err
+            );
+        }
+
+        self.core.select_session(sessions);
+
+        if let Some(channel) = self.core.program_needed() {
+            let program = self.fetch_program(&channel).unwrap_or_else(|err| {
+                debug!(
+                    "Couldn't look up the programme on channel {}: {}",
+                    channel, err
                 );
-            }
+                None
+            });
+            self.core.set_program(channel, program);
+        }
 
-            let mut state = self.get_state();
+        Ok(())
+    }
 
-            if state.len() > 128 {
-                state = state.chars().take(128).collect();
-            } else if state.len() < 3 {
-                // Add three zero width joiners due to discord requiring a minimum length of 3 chars in statuses
-                state += "‎‎‎";
-            }
+    fn fetch_program(&self, channel: &str) -> JfResult<Option<Program>> {
+        let programs: Vec<Program> = self
+            .session_reqwest()
+            .get(self.core.servers[self.core.session_server].program_url(channel)?)
+            .send()?
+            .error_for_status()?
+            .json::<Listing<Program>>()?
+            .into();
 
-            let mut details = self.get_details();
+        Ok(programs.into_iter().next())
+    }
 
-            if details.len() > 128 {
-                details = details.chars().take(128).collect();
-            } else if details.len() < 3 {
-                // add three (3) zero width joiners
-                details += "‎‎‎";
-            }
+    fn get_server_sessions(&mut self, server: usize) -> JfResult<Vec<RawSession>> {
+        if !self.core.servers[server].users_resolved() {
+            let users = self.fetch_users(server);
+            self.core.servers[server].resolve_users(users);
+        }
 
-            let mut image_text = self.get_image_text();
+        let connection = &mut self.connections[server];
+        let server = &self.core.servers[server];
 
-            if image_text.is_empty() {
-                image_text = format!("Jellyfin-RPC v{}", VERSION.unwrap_or("UNKNOWN"));
-            }
+        if let Some(sessions) = connection
+            .session_socket(server, &self.update_signal)
+            .and_then(|s| s.sessions())
+        {
+            debug!("Using sessions pushed over the websocket");
+            return Ok(sessions);
+        }
 
-            if image_text.len() > 128 {
-                image_text = image_text.chars().take(128).collect();
-            } else if image_text.len() < 3 {
-                // add three zero width joiners
-                image_text += "‎‎‎";
+        Ok(connection
+            .reqwest
+            .get(server.sessions_url()?)
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    fn fetch_users(&self, server: usize) -> JfResult<Vec<User>> {
+        Ok(self.connections[server]
+            .reqwest
+            .get(self.core.servers[server].users_url()?)
+            .send()?
+            .error_for_status()?
+            .json::<Listing<User>>()?
+            .into())
+    }
+
+    fn fetch_system_info(&self, server: usize) -> JfResult<SystemInfo> {
+        Ok(self.connections[server]
+            .reqwest
+            .get(self.core.servers[server].system_info_url()?)
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    /// Http client of the server the current session is on
+    pub(crate) fn session_reqwest(&self) -> &reqwest::blocking::Client {
+        &self.connections[self.core.session_server].reqwest
+    }
+
+    /// Picks the image shown on the activity, falls back to the default image if anything goes wrong
+    fn get_image_url(&self) -> JfResult<Url> {
+        if !self.core.show_images {
+            return self.core.default_image_url();
+        }
+
+        match self.find_image() {
+            Ok(image_url) => Ok(image_url),
+            Err(err) => {
+                debug!("Couldn't get an image, using default: {}", err);
+                self.core.default_image_url()
             }
+        }
+    }
 
-            assets = assets.large_text(image_text.as_str());
+    /// Goes through the image sources of the media type until one of them has an image
+    fn find_image(&self) -> JfResult<Url> {
+        for image in self.core.image_candidates() {
+            let image_url = if self.core.image_hosts.is_empty() {
+                self.get_image(&image)
+            } else {
+                self.get_hosted_image(&image)
+            };
 
-            match session.now_playing_item.media_type {
-                MediaType::Book => (),
-                MediaType::Music | MediaType::AudioBook => {
-                    activity = activity.activity_type(ActivityType::Listening)
+            match image_url {
+                Err(JfError::NoImage) => {
+                    debug!("{} doesn't exist, trying the next image", image.path())
                 }
-                _ => activity = activity.activity_type(ActivityType::Watching),
+                image_url => return image_url,
             }
+        }
 
-            let status_display_type = self.get_status_display_type();
+        Err(JfError::NoImage)
+    }
 
-            activity = activity
-                .timestamps(timestamps)
-                .assets(assets)
-                .details(&details)
-                .state(&state)
-                .status_display_type(status_display_type.into());
+    /// Uploads the image from Jellyfin to the image hosts, unless it has been uploaded before
+    fn get_hosted_image(&self, image: &JellyfinImage) -> JfResult<Url> {
+        let cache = self.core.image_cache.as_deref();
+
+        if let Some(image_url) = host::find_cached(
+            &self.core.image_hosts,
+            cache,
+            &image.cache_id(),
+            image.tag.as_deref(),
+        )? {
+            return Ok(image_url);
+        }
 
-            self.discord_ipc_client.set_activity(activity)?;
+        let image_bytes = self
+            .session_reqwest()
+            .get(self.get_image(image)?)
+            .send()?
+            .bytes()?;
+        let image_bytes = prepare_image(self.core.process_images, &image_bytes)?;
+
+        host::upload(
+            &self.core.image_hosts,
+            cache,
+            &image.cache_id(),
+            image.tag.as_deref(),
+            image_bytes,
+        )
+    }
+
+    /// Url to `image` on Jellyfin, asks the server whether it exists when the tag isn't known
+    fn get_image(&self, image: &JellyfinImage) -> JfResult<Url> {
+        let image_url = self.core.image_url(image)?;
 
-            return Ok(format!("{} | {}", details, state));
+        if image.tag.is_some() {
+            return Ok(image_url);
         }
-        Ok(String::new())
+
+        let response = self.session_reqwest().head(image_url.as_ref()).send()?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(JfError::NoImage);
+        }
+
+        response.error_for_status()?;
+        Ok(image_url)
     }
 
-    fn get_session(&mut self) -> JfResult<()> {
-        let sessions: Vec<RawSession> = self
+    /// Reload the library list from Jellyfin and filter out the user-provided blacklisted libraries
+    pub fn reload_blacklist(&mut self) {
+        for server in 0..self.core.servers.len() {
+            self.reload_server_blacklist(server);
+        }
+    }
+
+    fn reload_server_blacklist(&mut self, server: usize) {
+        let virtual_folders = self.fetch_virtual_folders(server);
+        self.core.servers[server].set_blacklist(virtual_folders);
+    }
+
+    fn fetch_virtual_folders(&self, server: usize) -> JfResult<Vec<VirtualFolder>> {
+        Ok(self.connections[server]
             .reqwest
-            .get(self.url.join("Sessions")?)
+            .get(
+                self.core.servers[server]
+                    .url
+                    .join("Library/VirtualFolders")?,
+            )
             .send()?
-            .json()?;
+            .error_for_status()?
+            .json::<Listing<VirtualFolder>>()?
+            .into())
+    }
+}
+
+/// Options and state shared between `Client` and `AsyncClient`.
+///
+/// Nothing in here talks to Jellyfin or Discord, the clients fetch the data and hand it over.
+pub(crate) struct Core {
+    servers: Vec<Server>,
+    session: Option<Session>,
+    /// Index of the server `session` is on
+    session_server: usize,
+    /// Audio session shown alongside a video session when `combine_sessions` is enabled
+    companion_session: Option<Session>,
+    /// When each session was first seen playing its current item, keyed by server and session id
+    session_starts: HashMap<(usize, String), (String, Instant)>,
+    session_priority: Vec<SessionPriority>,
+    combine_sessions: bool,
+    session_allow: SessionFilter,
+    session_deny: SessionFilter,
+    /// Only set when sessions from other machines should be ignored
+    local_machine: Option<LocalMachine>,
+    last_presence: Option<Presence>,
+    /// Live TV programme that was looked up last and the channel it's on, reused until it ends
+    live_program: Option<(String, Program)>,
+    buttons: Option<Vec<Button>>,
+    music_display_options: DisplayOptions,
+    movies_display_options: DisplayOptions,
+    episodes_display_options: DisplayOptions,
+    livetv_display_options: DisplayOptions,
+    books_display_options: DisplayOptions,
+    audiobooks_display_options: DisplayOptions,
+    /// Display options of the media types that can't be configured
+    builtin_display_options: Vec<(MediaType, DisplayOptions)>,
+    /// Used for channel items and media types that aren't recognized
+    generic_display_options: DisplayOptions,
+    show_paused: bool,
+    show_images: bool,
+    /// Tried in order until one of them accepts the image
+    image_hosts: Vec<Arc<dyn ImageHost>>,
+    image_cache: Option<Arc<ImageCache>>,
+    /// Media types that don't use the default image sources
+    image_sources: Vec<(MediaType, Vec<ImageSource>)>,
+    process_images: bool,
+    large_image_text: String,
+}
+
+impl Core {
+    /// Picks the session to display out of every session on the servers,
+    /// `sessions` holds the session list of every server in `servers`.
+    fn select_session(&mut self, sessions: Vec<Vec<RawSession>>) {
+        let mut candidates = Vec::new();
+
+        if let Some(local) = self.local_machine.as_mut() {
+            local.refresh();
+        }
+
+        for (server, sessions) in sessions.into_iter().enumerate() {
+            debug!(
+                "Found {} sessions on {}",
+                sessions.len(),
+                self.servers[server].url
+            );
+
+            for session in sessions {
+                debug!("Session username is {:?}", session.user_name);
 
-        debug!("Found {} sessions", sessions.len());
+                if !self.servers[server].matches_user(&session) {
+                    continue;
+                }
+
+                if !self.session_allow.allows(&session) || self.session_deny.denies(&session) {
+                    debug!(
+                        "Session from {:?} on {:?} is filtered out",
+                        session.client, session.device_name
+                    );
+                    continue;
+                }
 
-        for session in sessions {
-            debug!("Session username is {:?}", session.user_name);
-            if let Some(username) = session.user_name.as_ref() {
                 if self
-                    .usernames
-                    .iter()
-                    .all(|u| username.to_lowercase() != u.to_lowercase())
+                    .local_machine
+                    .as_ref()
+                    .is_some_and(|local| !local.matches(&session))
                 {
+                    debug!(
+                        "Session from {:?} on {:?} isn't on this machine",
+                        session.remote_end_point, session.device_name
+                    );
                     continue;
                 }
 
@@ -290,12 +615,300 @@ impl Client {
                     continue;
                 }
 
-                self.session = Some(session);
-                return Ok(());
+                candidates.push((server, session));
             }
         }
-        self.session = None;
-        Ok(())
+
+        self.track_session_starts(&candidates);
+
+        // Stable sort, sessions the policy considers equal keep the server order
+        candidates.sort_by(|a, b| self.compare_sessions(a, b));
+
+        // A blacklisted session is only picked when there's nothing else, so the outcome says why
+        let selected = candidates
+            .iter()
+            .position(|(server, session)| !self.is_blacklisted(*server, session))
+            .unwrap_or_default();
+
+        let mut candidates = candidates.into_iter();
+        let (session_server, session) = candidates.nth(selected).unzip();
+        self.session_server = session_server.unwrap_or_default();
+        self.session = session;
+
+        self.companion_session = match &self.session {
+            Some(session) if self.combine_sessions && session.is_video() => candidates
+                .find(|(server, c)| {
+                    c.is_audio() && !c.play_state.is_paused && !self.is_blacklisted(*server, c)
+                })
+                .map(|(_, c)| c),
+            _ => None,
+        };
+    }
+
+    /// Remembers when each session started playing its current item,
+    /// sessions that were already playing when they were first seen share the same time.
+    fn track_session_starts(&mut self, candidates: &[(usize, Session)]) {
+        let now = Instant::now();
+        let mut seen = HashMap::new();
+
+        for (server, session) in candidates {
+            let Some(id) = &session.id else {
+                continue;
+            };
+            let key = (*server, id.clone());
+            let item = &session.now_playing_item.id;
+
+            let start = match self.session_starts.remove(&key) {
+                Some((started_item, start)) if &started_item == item => start,
+                _ => now,
+            };
+
+            seen.insert(key, (item.clone(), start));
+        }
+
+        self.session_starts = seen;
+    }
+
+    /// When the session started playing its current item, `None` if it isn't known
+    fn session_start(&self, server: usize, session: &Session) -> Option<Instant> {
+        let id = session.id.as_ref()?;
+
+        self.session_starts
+            .get(&(server, id.clone()))
+            .map(|(_, start)| *start)
+    }
+
+    /// Orders two sessions by the `session_priority` rules, the first rule that isn't a tie decides
+    fn compare_sessions(
+        &self,
+        (a_server, a): &(usize, Session),
+        (b_server, b): &(usize, Session),
+    ) -> Ordering {
+        self.session_priority
+            .iter()
+            .map(|rule| match rule {
+                SessionPriority::MostRecentlyStarted => self
+                    .session_start(*b_server, b)
+                    .cmp(&self.session_start(*a_server, a)),
+                SessionPriority::PreferVideo => b.is_video().cmp(&a.is_video()),
+                SessionPriority::PreferClients(clients) => {
+                    let rank = |s: &Session| {
+                        clients
+                            .iter()
+                            .position(|c| {
+                                [&s.client, &s.device_name]
+                                    .into_iter()
+                                    .flatten()
+                                    .any(|name| name.to_lowercase() == c.to_lowercase())
+                            })
+                            .unwrap_or(clients.len())
+                    };
+                    rank(a).cmp(&rank(b))
+                }
+                SessionPriority::PreferUnpaused => {
+                    a.play_state.is_paused.cmp(&b.play_state.is_paused)
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    /// Returns the reason why the current session shouldn't be displayed, if there is one.
+    fn check_session(&self) -> JfResult<Option<ActivityOutcome>> {
+        let Some(session) = self.session.as_ref() else {
+            return Ok(Some(ActivityOutcome::NothingPlaying));
+        };
+
+        if self.is_blacklisted(self.session_server, session) {
+            return Ok(Some(ActivityOutcome::Blacklisted));
+        }
+
+        if !self.show_paused && matches!(session.get_time()?, PlayTime::Paused) {
+            return Ok(Some(ActivityOutcome::HiddenWhilePaused));
+        }
+
+        Ok(None)
+    }
+
+    /// Remembers the presence that was just displayed and whether it changed since last time
+    fn presence_shown(&mut self, presence: Box<Presence>) -> ActivityOutcome {
+        if self.last_presence.as_ref() == Some(&*presence) {
+            return ActivityOutcome::Unchanged;
+        }
+
+        self.last_presence = Some((*presence).clone());
+        ActivityOutcome::Updated(presence)
+    }
+
+    /// Channel whose programme has to be looked up, `None` if the session doesn't need one
+    fn program_needed(&mut self) -> Option<String> {
+        let item = &mut self.session.as_mut()?.now_playing_item;
+
+        if item.media_type != MediaType::LiveTv {
+            return None;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        if let Some(program) = item.current_program.as_ref().filter(|p| !p.has_ended(now)) {
+            self.live_program = Some((item.id.clone(), program.clone()));
+            return None;
+        }
+
+        match &self.live_program {
+            Some((channel, program)) if *channel == item.id && !program.has_ended(now) => {
+                item.current_program = Some(program.clone());
+                None
+            }
+            _ => Some(item.id.clone()),
+        }
+    }
+
+    /// Attaches the programme that was looked up for `program_needed`
+    fn set_program(&mut self, channel: String, program: Option<Program>) {
+        if let Some(program) = &program {
+            self.live_program = Some((channel, program.clone()));
+        }
+
+        if let Some(session) = self.session.as_mut() {
+            session.now_playing_item.current_program = program;
+        }
+    }
+
+    fn is_live_tv(&self) -> bool {
+        self.session
+            .as_ref()
+            .is_some_and(|s| s.now_playing_item.media_type == MediaType::LiveTv)
+    }
+
+    fn default_image_url(&self) -> JfResult<Url> {
+        if self.is_live_tv() {
+            Ok(Url::from_str("https://i.imgur.com/XxdHOqm.png")?)
+        } else {
+            Ok(Url::from_str("https://i.imgur.com/oX6vcds.png")?)
+        }
+    }
+
+    /// Images of the current session that may be shown, in the order they're tried
+    fn image_candidates(&self) -> Vec<JellyfinImage> {
+        let session = self.session.as_ref().unwrap();
+        let media_type = session.now_playing_item.media_type;
+
+        let sources = self
+            .image_sources
+            .iter()
+            .find(|(kind, _)| *kind == media_type)
+            .map(|(_, sources)| sources.clone())
+            .unwrap_or_else(|| ImageSource::defaults(media_type));
+
+        sources
+            .iter()
+            .filter_map(|source| source.resolve(session))
+            .collect()
+    }
+
+    /// Url to `image` on the Jellyfin server of the current session
+    fn image_url(&self, image: &JellyfinImage) -> JfResult<Url> {
+        Ok(self.servers[self.session_server].url.join(&image.path())?)
+    }
+
+    /// Builds the presence for the current session, `check_session` has to be called first.
+    fn build_presence(&self, image_url: Url) -> JfResult<Presence> {
+        let session = self.session.as_ref().unwrap();
+
+        let mut start_timestamp = None;
+        let mut end_timestamp = None;
+        let mut paused = false;
+
+        match session.get_time()? {
+            PlayTime::Some(start, end) => {
+                start_timestamp = Some(start);
+                end_timestamp = Some(end);
+            }
+            PlayTime::None => (),
+            PlayTime::Paused => paused = true,
+        }
+
+        let mut state = self.get_state();
+
+        if let Some(companion) = &self.companion_session {
+            let artists = companion.format_artists();
+
+            let listening = if artists.is_empty() {
+                companion.now_playing_item.name.clone()
+            } else {
+                format!("{} by {}", companion.now_playing_item.name, artists)
+            };
+
+            state = if state.is_empty() {
+                format!("also listening to {}", listening)
+            } else {
+                format!("{} · also listening to {}", state, listening)
+            };
+        }
+
+        if state.len() > 128 {
+            state = state.chars().take(128).collect();
+        } else if state.len() < 3 {
+            // Add three zero width joiners due to discord requiring a minimum length of 3 chars in statuses
+            state += "‎‎‎";
+        }
+
+        let mut details = self.get_details();
+
+        if details.len() > 128 {
+            details = details.chars().take(128).collect();
+        } else if details.len() < 3 {
+            // add three (3) zero width joiners
+            details += "‎‎‎";
+        }
+
+        let mut image_text = self.get_image_text();
+
+        if image_text.is_empty() && !self.large_image_text.is_empty() {
+            image_text = self.large_image_text.clone();
+        } else if image_text.is_empty() {
+            image_text = format!("Jellyfin-RPC v{}", VERSION.unwrap_or("UNKNOWN"));
+        }
+
+        if image_text.len() > 128 {
+            image_text = image_text.chars().take(128).collect();
+        } else if image_text.len() < 3 {
+            // add three zero width joiners
+            image_text += "‎‎‎";
+        }
+
+        let activity_type = match session.now_playing_item.media_type {
+            MediaType::Book => None,
+            MediaType::Music | MediaType::AudioBook => Some(PresenceType::Listening),
+            _ => Some(PresenceType::Watching),
+        };
+
+        let (small_image_url, small_image_text) = if paused {
+            (
+                Some(PAUSED_IMAGE_URL.to_string()),
+                Some("Paused".to_string()),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(Presence {
+            details,
+            state,
+            large_image_url: image_url.to_string(),
+            large_image_text: image_text,
+            small_image_url,
+            small_image_text,
+            start_timestamp,
+            end_timestamp,
+            buttons: self.get_buttons().unwrap_or_default(),
+            activity_type,
+            status_display_type: self.get_status_display_type(),
+        })
     }
 
     fn get_buttons(&self) -> Option<Vec<Button>> {
@@ -364,436 +977,193 @@ impl Client {
         None
     }
 
-    fn get_image(&self) -> JfResult<Url> {
-        let session = self.session.as_ref().unwrap();
-
-        let path = "Items/".to_string() + &session.item_id + "/Images/Primary";
-
-        let image_url = self.url.join(&path)?;
-
-        if self
-            .reqwest
-            .get(image_url.as_ref())
-            .send()?
-            .text()?
-            .contains("does not have an image of type Primary")
-        {
-            Err(Box::new(JfError::NoImage))
-        } else {
-            Ok(image_url)
+    fn display_options(&self, media_type: MediaType) -> &DisplayOptions {
+        match media_type {
+            MediaType::Music => &self.music_display_options,
+            MediaType::Movie => &self.movies_display_options,
+            MediaType::Episode => &self.episodes_display_options,
+            MediaType::LiveTv => &self.livetv_display_options,
+            MediaType::Book => &self.books_display_options,
+            MediaType::AudioBook => &self.audiobooks_display_options,
+            media_type => self
+                .builtin_display_options
+                .iter()
+                .find(|(m, _)| *m == media_type)
+                .map(|(_, options)| options)
+                .unwrap_or(&self.generic_display_options),
         }
     }
 
-    fn sanitize_display_format(input: &str) -> String {
-        // Remove unnecessary spaces
-        let mut result = input.split_whitespace().collect::<Vec<&str>>().join(" ");
-
-        // Remove duplicated separators
-        while result.contains("{sep}{sep}") || result.contains("{sep} {sep}") {
-            result = result.replace("{sep}{sep}", "{sep}");
-            result = result.replace("{sep} {sep}", "{sep}");
-        }
-
-        // Remove unnecessary separators
-        while result.starts_with("{sep}") {
-            result = result
-                .drain(5..)
-                .collect::<String>()
-                .trim_start()
-                .to_string();
-        }
+    /// Values of the placeholders that can be used in the display formats of the current session
+    fn placeholder_values(&self) -> Values {
+        let session = self.session.as_ref().unwrap();
+        let item = &session.now_playing_item;
 
-        while result.ends_with("{sep}") {
-            result = result
-                .drain(..result.len() - 5)
-                .collect::<String>()
-                .trim_end()
-                .to_string();
+        Values {
+            named: placeholders(item.media_type)
+                .iter()
+                .map(|placeholder| (placeholder.name, placeholder.value(session)))
+                .collect(),
+            raw: item.raw.clone(),
         }
-
-        result
     }
 
-    fn parse_music_display(&self, input: &str) -> String {
-        let mut result = input.trim().to_string();
+    fn get_details(&self) -> String {
         let session = self.session.as_ref().unwrap();
+        let options = self.display_options(session.now_playing_item.media_type);
 
-        let separator = &self.music_display_options.separator;
-        let track = session.now_playing_item.name.as_ref();
-        let artists = session.format_artists();
-        let genres = session
-            .now_playing_item
-            .genres
-            .as_ref()
-            .unwrap_or(&vec!["".to_string()])
-            .join(", ");
-        let year = session
-            .now_playing_item
-            .production_year
-            .map(|y| y.to_string())
-            .unwrap_or_default();
-        let album = session
-            .now_playing_item
-            .album
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-
-        result = result
-            .replace("{track}", track)
-            .replace("{album}", &album)
-            .replace("{artists}", &artists)
-            .replace("{genres}", &genres)
-            .replace("{year}", &year)
-            .replace("{version}", VERSION.unwrap_or("UNKNOWN"));
-
-        Self::sanitize_display_format(&result).replace("{sep}", separator)
+        options
+            .details
+            .render(&self.placeholder_values(), &options.separator)
     }
 
-    fn parse_movies_display(&self, input: &str) -> String {
-        let mut result = input.trim().to_string();
+    fn get_state(&self) -> String {
         let session = self.session.as_ref().unwrap();
+        let options = self.display_options(session.now_playing_item.media_type);
 
-        let separator = &self.movies_display_options.separator;
-        let title = session.now_playing_item.name.as_ref();
-        let original_title = session
-            .now_playing_item
-            .original_title
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-        let genres = &session
-            .now_playing_item
-            .genres
-            .as_ref()
-            .unwrap_or(&vec!["".to_string()])
-            .join(", ");
-        let year = session
-            .now_playing_item
-            .production_year
-            .map(|y| y.to_string())
-            .unwrap_or_default();
-        let critic_score = &session
-            .now_playing_item
-            .critic_rating
-            .map(|s| format!("🍅 {}/100", s))
-            .unwrap_or_default();
-        let community_score = &session
-            .now_playing_item
-            .community_rating
-            .map(|s| format!("⭐ {:.1}/10", s))
-            .unwrap_or_default();
-
-        result = result
-            .replace("{title}", title)
-            .replace("{original-title}", &original_title)
-            .replace("{genres}", &genres)
-            .replace("{year}", &year)
-            .replace("{critic-score}", critic_score)
-            .replace("{community-score}", community_score)
-            .replace("{version}", VERSION.unwrap_or("UNKNOWN"));
-
-        Self::sanitize_display_format(&result).replace("{sep}", separator)
+        options
+            .state
+            .render(&self.placeholder_values(), &options.separator)
     }
 
-    fn parse_episodes_display(&self, input: &str) -> String {
-        let mut result = input.trim().to_string();
+    fn get_status_display_type(&self) -> StatusType {
         let session = self.session.as_ref().unwrap();
 
-        let separator = &self.episodes_display_options.separator;
-        let show_title = session
-            .now_playing_item
-            .series_name
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-        let episode_title = session.now_playing_item.name.as_ref();
-        let original_title = session
-            .now_playing_item
-            .original_title
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-        let season = session.now_playing_item.parent_index_number.unwrap_or(0);
-        let year = session
-            .now_playing_item
-            .production_year
-            .map(|y| y.to_string())
-            .unwrap_or_default();
-        let genres = session
-            .now_playing_item
-            .genres
-            .as_ref()
-            .unwrap_or(&vec!["".to_string()])
-            .join(", ");
-        let studio = session
-            .now_playing_item
-            .series_studio
-            .as_ref()
-            .unwrap_or(&"".to_string())
-            .clone();
-
-        // One episode on Jellyfin can span across multiple actual episodes
-        // For example E01-03 is 3 episodes in one media file
-        let episode_range = (
-            session.now_playing_item.index_number.unwrap_or(0),
-            session.now_playing_item.index_number_end,
-        );
-        result = result
-            .replace("{show-title}", &show_title)
-            .replace("{title}", episode_title)
-            .replace("{original-title}", &original_title)
-            .replace(
-                "{episode}",
-                &match episode_range {
-                    (first, Some(last)) => format!("{}-{}", first, last),
-                    (episode, None) => format!("{}", episode),
-                },
-            )
-            .replace(
-                "{episode-padded}",
-                &match episode_range {
-                    (first, Some(last)) => format!("{:02}-{:02}", first, last),
-                    (episode, None) => format!("{:02}", episode),
-                },
-            )
-            .replace("{season}", &season.to_string())
-            .replace("{season-padded}", &format!("{:02}", season))
-            .replace("{year}", &year)
-            .replace("{genres}", &genres)
-            .replace("{studio}", &studio)
-            .replace("{version}", VERSION.unwrap_or("UNKNOWN"));
-
-        Self::sanitize_display_format(&result).replace("{sep}", separator)
+        self.display_options(session.now_playing_item.media_type)
+            .status_display_type
+            .clone()
     }
 
-    fn get_details(&self) -> String {
+    fn get_image_text(&self) -> String {
         let session = self.session.as_ref().unwrap();
+        let options = self.display_options(session.now_playing_item.media_type);
 
-        match session.now_playing_item.media_type {
-            MediaType::Music => {
-                let display_details_format = &self
-                    .music_display_options
-                    .display
-                    .details_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_music_display(
-                    display_details_format
-                        .replace("{__default}", "{track}")
-                        .as_str(),
-                )
-            }
-            MediaType::Movie => {
-                let display_details_format = &self
-                    .movies_display_options
-                    .display
-                    .details_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_movies_display(
-                    display_details_format
-                        .replace("{__default}", "{title}")
-                        .as_str(),
-                )
-            }
-            MediaType::Episode => {
-                let display_details_format = &self
-                    .episodes_display_options
-                    .display
-                    .details_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_episodes_display(
-                    display_details_format
-                        .replace("{__default}", "{show-title}")
-                        .as_str(),
-                )
-            }
-            MediaType::AudioBook => session
-                .now_playing_item
-                .album
-                .as_ref()
-                .map(|a| a.to_string())
-                .unwrap_or_else(|| session.now_playing_item.name.to_string()),
-            _ => session.now_playing_item.name.to_string(),
-        }
+        options
+            .image
+            .render(&self.placeholder_values(), &options.separator)
     }
 
-    fn get_state(&self) -> String {
-        let session = self.session.as_ref().unwrap();
-
-        match session.now_playing_item.media_type {
-            MediaType::Episode => {
-                let display_state_format = &self
-                    .episodes_display_options
-                    .display
-                    .state_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_episodes_display(
-                    display_state_format.replace("{__default}", "").as_str(),
-                )
-            }
-            MediaType::LiveTv => "Live TV".to_string(),
-            MediaType::Music => {
-                let display_state_format = &self
-                    .music_display_options
-                    .display
-                    .state_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_music_display(
-                    display_state_format
-                        .replace("{__default}", "By {artists} {sep} ")
-                        .as_str(),
-                )
-            }
-            MediaType::Book => {
-                let mut state = String::new();
-
-                if let Some(position_ticks) = session.play_state.position_ticks {
-                    let ticks_to_pages = 10000;
-
-                    let page = position_ticks / ticks_to_pages;
-
-                    state += &format!("Reading page {}", page);
-                }
-
-                state
-            }
-            MediaType::AudioBook => {
-                let mut state = String::new();
-
-                let artists = session.format_artists();
+    fn is_blacklisted(&self, server: usize, session: &Session) -> bool {
+        let blacklist = &self.servers[server].blacklist;
 
-                let genres = session
-                    .now_playing_item
-                    .genres
-                    .as_ref()
-                    .unwrap_or(&vec!["".to_string()])
-                    .join(", ");
+        blacklist
+            .media_types
+            .iter()
+            .any(|m| m == &session.now_playing_item.media_type)
+            || blacklist.check_item(&session.now_playing_item)
+    }
+}
 
-                if !artists.is_empty() {
-                    state += &format!("By {}", artists)
-                }
+/// What `set_activity` did, or why nothing is displayed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActivityOutcome {
+    /// A new presence was built, `set_activity` displayed it on Discord
+    Updated(Box<Presence>),
+    /// The presence displayed on Discord is the same as last time
+    Unchanged,
+    /// Nothing should be displayed anymore so the activity was cleared
+    Cleared,
+    /// The current item is blacklisted by media type or library
+    Blacklisted,
+    /// Playback is paused and `show_paused` is disabled
+    HiddenWhilePaused,
+    /// None of the configured users are playing anything
+    NothingPlaying,
+}
 
-                if !state.is_empty() && !genres.is_empty() {
-                    state += " - "
-                }
+/// Everything displayed on Discord for the current session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    /// First line of the activity
+    pub details: String,
+    /// Second line of the activity
+    pub state: String,
+    /// Url to the large image, either the media's cover or the default Jellyfin logo
+    pub large_image_url: String,
+    /// Text shown when hovering over the large image
+    pub large_image_text: String,
+    /// Url to the small image, only set while paused
+    pub small_image_url: Option<String>,
+    /// Text shown when hovering over the small image
+    pub small_image_text: Option<String>,
+    /// Unix timestamp (in seconds) of when playback started
+    pub start_timestamp: Option<i64>,
+    /// Unix timestamp (in seconds) of when playback ends
+    pub end_timestamp: Option<i64>,
+    pub buttons: Vec<Button>,
+    /// `None` shows up as "Playing" on Discord
+    pub activity_type: Option<PresenceType>,
+    pub status_display_type: StatusType,
+}
 
-                state += &genres;
+impl Presence {
+    fn to_activity(&self) -> Activity<'_> {
+        let mut assets = Assets::new()
+            .large_image(self.large_image_url.as_str())
+            .large_text(self.large_image_text.as_str());
 
-                state
-            }
-            MediaType::Movie => {
-                let display_state_format = &self
-                    .movies_display_options
-                    .display
-                    .state_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_movies_display(display_state_format.replace("{__default}", "").as_str())
-            }
-            _ => session
-                .now_playing_item
-                .genres
-                .as_ref()
-                .unwrap_or(&vec!["".to_string()])
-                .join(", "),
+        if let Some(small_image_url) = &self.small_image_url {
+            assets = assets.small_image(small_image_url.as_str());
         }
-    }
 
-    fn get_status_display_type(&self) -> StatusType {
-        let session = self.session.as_ref().unwrap();
-        match session.now_playing_item.media_type {
-            MediaType::Episode => self.episodes_display_options.status_display_type.clone(),
-            MediaType::Movie => self.movies_display_options.status_display_type.clone(),
-            MediaType::Music => self.music_display_options.status_display_type.clone(),
-            _ => Default::default(),
+        if let Some(small_image_text) = &self.small_image_text {
+            assets = assets.small_text(small_image_text.as_str());
         }
-    }
 
-    fn get_image_text(&self) -> String {
-        let session = self.session.as_ref().unwrap();
+        let mut timestamps = Timestamps::new();
 
-        match session.now_playing_item.media_type {
-            MediaType::Music => {
-                let display_image_format = &self
-                    .music_display_options
-                    .display
-                    .image_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_music_display(display_image_format)
-            }
-            MediaType::Movie => {
-                let display_image_format = &self
-                    .movies_display_options
-                    .display
-                    .image_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_movies_display(display_image_format)
-            }
-            MediaType::Episode => {
-                let display_image_format = &self
-                    .episodes_display_options
-                    .display
-                    .image_text
-                    .as_ref()
-                    .unwrap();
-                self.parse_episodes_display(display_image_format)
-            }
-            _ => "".to_string(),
+        if let Some(start) = self.start_timestamp {
+            timestamps = timestamps.start(start);
         }
-    }
 
-    fn check_blacklist(&self) -> JfResult<bool> {
-        let session = self.session.as_ref().unwrap();
+        if let Some(end) = self.end_timestamp {
+            timestamps = timestamps.end(end);
+        }
 
-        if self
-            .blacklist
-            .media_types
-            .iter()
-            .any(|m| m == &session.now_playing_item.media_type)
-        {
-            return Ok(true);
+        let mut activity = Activity::new();
+
+        if !self.buttons.is_empty() {
+            activity = activity.buttons(
+                self.buttons
+                    .iter()
+                    .map(|b| ActButton::new(&b.name, &b.url))
+                    .collect(),
+            );
         }
 
-        if self.blacklist.check_item(&session.now_playing_item) {
-            return Ok(true);
+        if let Some(activity_type) = &self.activity_type {
+            activity = activity.activity_type(activity_type.clone().into());
         }
 
-        Ok(false)
+        activity
+            .timestamps(timestamps)
+            .assets(assets)
+            .details(&self.details)
+            .state(&self.state)
+            .status_display_type(self.status_display_type.clone().into())
     }
 
-    /// Fetch the virtual folder list and filter out the blacklisted libraries
-    fn fetch_blacklist(&self) -> JfResult<Vec<VirtualFolder>> {
-        let virtual_folders: Vec<VirtualFolder> = self
-            .reqwest
-            .get(self.url.join("Library/VirtualFolders")?)
-            .send()?
-            .json()?;
-
-        Ok(virtual_folders
-            .into_iter()
-            .filter(|library_folder| {
-                self.blacklist
-                    .libraries_names
-                    .contains(library_folder.name.as_ref().unwrap_or(&String::new()))
-            })
-            .collect())
+    /// Short `details | state` description, useful for logging
+    pub fn summary(&self) -> String {
+        format!("{} | {}", self.details, self.state)
     }
+}
 
-    /// Reload the library list from Jellyfin and filter out the user-provided blacklisted libraries
-    fn reload_blacklist(&mut self) {
-        self.blacklist.libraries = match self.fetch_blacklist() {
-            Ok(blacklist) => BlacklistedLibraries::Initialized(blacklist, SystemTime::now()),
-            Err(err) => {
-                warn!("Failed to intialize blacklist: {}", err);
-                BlacklistedLibraries::Uninitialized
-            }
+/// Activity type shown on Discord, e.g. "Watching ..."
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PresenceType {
+    Playing,
+    Listening,
+    Watching,
+}
+
+impl From<PresenceType> for ActivityType {
+    fn from(x: PresenceType) -> Self {
+        match x {
+            PresenceType::Playing => Self::Playing,
+            PresenceType::Listening => Self::Listening,
+            PresenceType::Watching => Self::Watching,
         }
     }
 }
@@ -806,11 +1176,78 @@ pub struct EpisodeDisplayOptions {
 
 struct DisplayOptions {
     separator: String,
-    display: DisplayFormat,
+    details: Template,
+    state: Template,
+    image: Template,
     status_display_type: StatusType,
 }
 
+impl DisplayOptions {
+    /// Parses the display format, `{__default}` is replaced with the built-in format of the media type
+    fn new(
+        media_type: MediaType,
+        separator: String,
+        display: DisplayFormat,
+        status_display_type: StatusType,
+    ) -> JfResult<Self> {
+        let (default_details, default_state) = match media_type {
+            MediaType::Music => ("{track}", "[By {artists}] {sep} "),
+            MediaType::Movie => ("{title}", ""),
+            MediaType::Episode => ("{show-title}", ""),
+            MediaType::LiveTv => ("{channel}", "Live TV[ {sep} {program}[: {episode-title}]]"),
+            MediaType::Book => (
+                "{title}",
+                "[{percent}%] {sep} [{chapter}] {sep} [Page {page}[ of {page-count}]]",
+            ),
+            MediaType::AudioBook => ("{album|title}", "[By {artists}] {sep} {genres}"),
+            MediaType::MusicVideo => ("{title}", "[By {artists}] {sep} {genres}"),
+            MediaType::Video => ("{title}", "{album|folder}"),
+            MediaType::Trailer => ("{title}", "Trailer [{sep} {year}]"),
+            MediaType::Recording => ("{title}", "Recording [{sep} {show-title}]"),
+            MediaType::Photo => ("Viewing photos", "{album|folder}"),
+            MediaType::ChannelItem | MediaType::None => ("{title}", "{genres}"),
+        };
+
+        let parse = |format: Option<String>, default: &str| {
+            let format = format.unwrap_or_else(|| "{__default}".to_string());
+            Template::parse(
+                &format.replace("{__default}", default),
+                placeholders(media_type),
+            )
+        };
+
+        Ok(Self {
+            separator,
+            details: parse(display.details_text, default_details)?,
+            state: parse(display.state_text, default_state)?,
+            image: parse(display.image_text, "")?,
+            status_display_type,
+        })
+    }
+}
+
 /// Represents the formatting details for `Display`.
+///
+/// Placeholders like `{title}` are replaced with information about the media,
+/// `{sep}` becomes the separator and unknown placeholders are rejected by `ClientBuilder::build()`.
+///
+/// - `{original-title|title}` uses the first placeholder that isn't empty
+/// - `[S{season}E{episode} ]` is left out entirely when one of the placeholders inside it is empty
+/// - `\` escapes the next character, e.g. `\[` for a literal bracket
+///
+/// `{raw:Field}` shows any field of the Jellyfin item, e.g. `{raw:OfficialRating}`.
+/// Nested fields are separated by dots like `{raw:UserData.PlayCount}`, `{raw:Taglines.0}` picks the first item of an array
+/// and `{raw:Studios.Name}` the name of every item. Arrays are shown as lists,
+/// objects by their `Name` field and missing fields count as empty.
+///
+/// Filters come after the placeholders and are applied in order, e.g. `{original-title|title|truncate:40}`:
+/// - `first:2` keeps the first items of a list like `{genres}` or `{artists}`
+/// - `truncate:40` shortens the value to at most 40 characters
+/// - `upper` and `lower` change the case
+/// - `pad:2` pads numbers with zeroes, `{episode|pad:2}` turns `1-3` into `01-03`
+/// - `date:%Y` formats dates like `{premiere-date}` with [strftime](https://docs.rs/chrono/latest/chrono/format/strftime/index.html) specifiers
+///
+/// Fields that are `None` use the built-in format of the media type.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct DisplayFormat {
     /// First line of the activity.
@@ -859,21 +1296,21 @@ impl From<EpisodeDisplayOptions> for DisplayFormat {
     fn from(value: EpisodeDisplayOptions) -> Self {
         let details_text = "{show-title}".to_string();
         let state_text = {
-            let (season_tag, episode_tag) = if value.prefix {
-                (
-                    "S{season-padded}".to_string(),
-                    "E{episode-padded}".to_string(),
-                )
+            let (season, episode) = if value.prefix {
+                ("{season-padded}", "{episode-padded}")
             } else {
-                ("S{season}".to_string(), "E{episode}".to_string())
+                ("{season}", "{episode}")
             };
 
             let divider = if value.divider { " - " } else { "" };
 
+            // Sections keep episodes without a season or episode number from showing `S` or `E` alone
+            let tags = format!("[S{}{}][E{}]", season, divider, episode);
+
             if value.simple {
-                format!("{}{}{}", season_tag, divider, episode_tag)
+                tags
             } else {
-                format!("{}{}{} {}", season_tag, divider, episode_tag, "{title}")
+                format!("{} {{title}}", tags)
             }
         };
         let image_text = "Jellyfin-RPC v{version}".to_string();
@@ -886,17 +1323,14 @@ impl From<EpisodeDisplayOptions> for DisplayFormat {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StatusType {
+    #[default]
     Name,
     State,
     Details,
 }
-impl Default for StatusType {
-    fn default() -> Self {
-        Self::Name
-    }
-}
 
 impl From<DiscordIpcStatusDisplayType> for StatusType {
     fn from(x: DiscordIpcStatusDisplayType) -> Self {
@@ -909,13 +1343,13 @@ impl From<DiscordIpcStatusDisplayType> for StatusType {
     }
 }
 
-impl Into<DiscordIpcStatusDisplayType> for StatusType {
-    fn into(self) -> DiscordIpcStatusDisplayType {
-        use DiscordIpcStatusDisplayType as T;
-        match self {
-            Self::Name => T::Name,
-            Self::State => T::State,
-            Self::Details => T::Details,
+impl From<StatusType> for DiscordIpcStatusDisplayType {
+    fn from(x: StatusType) -> Self {
+        use StatusType as T;
+        match x {
+            T::Name => Self::Name,
+            T::State => Self::State,
+            T::Details => Self::Details,
         }
     }
 }
@@ -935,6 +1369,68 @@ impl TryFrom<String> for StatusType {
     }
 }
 
+/// Rules for picking a session when a user has several active sessions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionPriority {
+    /// Prefer the session that started playing its current item most recently.
+    ///
+    /// Only changes seen by the client count, sessions that were already playing when it started are a tie.
+    MostRecentlyStarted,
+    /// Prefer movies, episodes and live tv over music and audiobooks
+    PreferVideo,
+    /// Prefer sessions from these clients or device names, in the order given
+    PreferClients(Vec<String>),
+    /// Prefer sessions that aren't paused
+    PreferUnpaused,
+}
+
+/// Lists of client applications, device names, device ids and remote addresses
+/// used to allow or deny sessions, values are compared case-insensitively.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionFilter {
+    /// Client applications, e.g. `"Jellyfin Web"` or `"Kodi"`
+    pub clients: Vec<String>,
+    /// Device names as shown on the Jellyfin dashboard
+    pub device_names: Vec<String>,
+    pub device_ids: Vec<String>,
+    /// IP addresses the sessions connect from
+    pub remote_end_points: Vec<String>,
+}
+
+impl SessionFilter {
+    fn lists<'a>(&'a self, session: &'a RawSession) -> [(&'a Vec<String>, &'a Option<String>); 4] {
+        [
+            (&self.clients, &session.client),
+            (&self.device_names, &session.device_name),
+            (&self.device_ids, &session.device_id),
+            (&self.remote_end_points, &session.remote_end_point),
+        ]
+    }
+
+    fn contains(list: &[String], value: &Option<String>) -> bool {
+        value.as_ref().is_some_and(|value| {
+            list.iter()
+                .any(|v| v.to_lowercase() == value.to_lowercase())
+        })
+    }
+
+    /// Every list that isn't empty contains the session's value
+    fn allows(&self, session: &RawSession) -> bool {
+        self.lists(session)
+            .into_iter()
+            .all(|(list, value)| list.is_empty() || Self::contains(list, value))
+    }
+
+    /// Any of the lists contains the session's value
+    fn denies(&self, session: &RawSession) -> bool {
+        self.lists(session)
+            .into_iter()
+            .any(|(list, value)| Self::contains(list, value))
+    }
+}
+
 struct Blacklist {
     media_types: Vec<MediaType>,
     libraries_names: Vec<String>,
@@ -970,25 +1466,24 @@ impl Blacklist {
     }
 }
 
-struct ImgurOptions {
-    enabled: bool,
-    client_id: String,
-    urls_location: String,
-}
-
-struct LitterboxOptions {
-    enabled: bool,
-    urls_location: String
-}
-
 /// Used to build a new Client
 #[derive(Default)]
 pub struct ClientBuilder {
     url: String,
     client_id: String,
     api_key: String,
+    server_kind: ServerKind,
+    device: Option<Device>,
     self_signed: bool,
     usernames: Vec<String>,
+    user_ids: Vec<String>,
+    servers: Vec<ServerConfig>,
+    session_priority: Vec<SessionPriority>,
+    combine_sessions: bool,
+    session_allow: SessionFilter,
+    session_deny: SessionFilter,
+    local_only: bool,
+    local_device_ids: Vec<String>,
     buttons: Option<Vec<Button>>,
     episode_divider: bool,
     episode_prefix: bool,
@@ -1002,17 +1497,28 @@ pub struct ClientBuilder {
     episodes_separator: String,
     episodes_display: DisplayFormat,
     episodes_status_display_type: StatusType,
+    livetv_separator: String,
+    livetv_display: DisplayFormat,
+    livetv_status_display_type: StatusType,
+    books_separator: String,
+    books_display: DisplayFormat,
+    books_status_display_type: StatusType,
+    audiobooks_separator: String,
+    audiobooks_display: DisplayFormat,
+    audiobooks_status_display_type: StatusType,
     blacklist_media_types: Vec<MediaType>,
     blacklist_libraries: Vec<String>,
     show_paused: bool,
     show_images: bool,
     use_imgur: bool,
     imgur_client_id: String,
-    imgur_urls_file_location: String,
     use_litterbox: bool,
-    litterbox_urls_file_location: String,
+    image_cache_location: String,
+    image_hosts: Vec<Arc<dyn ImageHost>>,
+    image_sources: Vec<(MediaType, Vec<ImageSource>)>,
     large_image_text: String,
     process_images: bool,
+    websocket: bool,
 }
 
 impl ClientBuilder {
@@ -1030,8 +1536,12 @@ impl ClientBuilder {
                 prefix: true,
                 simple: false,
             }),
+            livetv_separator: "-".to_string(),
+            books_separator: "-".to_string(),
+            audiobooks_separator: "-".to_string(),
             show_paused: true,
             process_images: true,
+            websocket: true,
             ..Default::default()
         }
     }
@@ -1052,7 +1562,8 @@ impl ClientBuilder {
         self
     }
 
-    /// Jellyfin API Key that will be used to gather data about what is being played.
+    /// Jellyfin API Key that will be used to gather data about what is being played,
+    /// an access token from [`Login`] works too.
     ///
     /// Has no default.
     pub fn api_key<T: Into<String>>(&mut self, api_key: T) -> &mut Self {
@@ -1060,6 +1571,25 @@ impl ClientBuilder {
         self
     }
 
+    /// Whether the server at `ClientBuilder::url()` runs Jellyfin or Emby.
+    ///
+    /// On Emby the API is reached under `emby/`, so the url should point at the root of the server,
+    /// e.g. `"http://localhost:8096"`.
+    ///
+    /// Defaults to `ServerKind::Jellyfin`.
+    pub fn server_kind(&mut self, kind: ServerKind) -> &mut Self {
+        self.server_kind = kind;
+        self
+    }
+
+    /// Identifies jellyfin-rpc as a device on the servers, required for access tokens from [`Login`].
+    ///
+    /// Not set by default, api keys are sent without describing the device.
+    pub fn device(&mut self, device: Device) -> &mut Self {
+        self.device = Some(device);
+        self
+    }
+
     /// Controls the use of certificate validation in reqwest.
     ///
     /// Defaults to `false`.
@@ -1092,6 +1622,148 @@ impl ClientBuilder {
         self
     }
 
+    /// Jellyfin user ids that should be matched when checking Jellyfin sessions,
+    /// unlike usernames these keep working when an account is renamed.
+    ///
+    /// Can be used together with `ClientBuilder::usernames()`.
+    ///
+    /// Has no default.
+    pub fn user_ids(&mut self, user_ids: Vec<String>) -> &mut Self {
+        self.user_ids = user_ids;
+        self
+    }
+
+    /// same as `ClientBuilder::user_ids()` but will only accept a single user id
+    ///
+    /// Has no default.
+    pub fn user_id<T: Into<String>>(&mut self, user_id: T) -> &mut Self {
+        self.user_ids = vec![user_id.into()];
+        self
+    }
+
+    /// Additional Jellyfin servers to look for sessions on,
+    /// next to the one set with `ClientBuilder::url()` and `ClientBuilder::api_key()`.
+    ///
+    /// Sessions from every server go through the same filters and `ClientBuilder::session_priority()`,
+    /// servers listed first win when the rules consider two sessions equal.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::{ClientBuilder, ServerConfig};
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user")
+    ///     .servers(vec![ServerConfig {
+    ///         url: "https://friends.example.com".to_string(),
+    ///         api_key: "efgh5678".to_string(),
+    ///         usernames: vec!["guest".to_string()],
+    ///         ..Default::default()
+    ///     }]);
+    /// ```
+    ///
+    /// Empty by default.
+    pub fn servers(&mut self, servers: Vec<ServerConfig>) -> &mut Self {
+        self.servers = servers;
+        self
+    }
+
+    /// Rules used to pick a session when several sessions match,
+    /// the first rule that prefers one session over the other decides.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::{ClientBuilder, SessionPriority};
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.session_priority(vec![
+    ///     SessionPriority::PreferUnpaused,
+    ///     SessionPriority::PreferVideo,
+    ///     SessionPriority::MostRecentlyStarted,
+    /// ]);
+    /// ```
+    ///
+    /// Defaults to no rules, which keeps the order Jellyfin returns the sessions in.
+    pub fn session_priority(&mut self, rules: Vec<SessionPriority>) -> &mut Self {
+        self.session_priority = rules;
+        self
+    }
+
+    /// Mention music or an audiobook playing in another session when a video is displayed,
+    /// e.g. `"S1E1 · also listening to Song by Artist"`.
+    ///
+    /// Defaults to `false`.
+    pub fn combine_sessions(&mut self, val: bool) -> &mut Self {
+        self.combine_sessions = val;
+        self
+    }
+
+    /// Only display sessions matching this filter,
+    /// every list that isn't empty has to contain the session's value.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::{ClientBuilder, SessionFilter};
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.allow_sessions(SessionFilter {
+    ///     clients: vec!["Jellyfin Web".to_string(), "Finamp".to_string()],
+    ///     ..Default::default()
+    /// });
+    /// ```
+    ///
+    /// Defaults to allowing every session.
+    pub fn allow_sessions(&mut self, filter: SessionFilter) -> &mut Self {
+        self.session_allow = filter;
+        self
+    }
+
+    /// Never display sessions matching this filter,
+    /// a session is ignored if any of its values is in one of the lists.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::{ClientBuilder, SessionFilter};
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.deny_sessions(SessionFilter {
+    ///     clients: vec!["Kodi".to_string()],
+    ///     device_names: vec!["Living Room TV".to_string()],
+    ///     ..Default::default()
+    /// });
+    /// ```
+    ///
+    /// Defaults to denying nothing.
+    pub fn deny_sessions(&mut self, filter: SessionFilter) -> &mut Self {
+        self.session_deny = filter;
+        self
+    }
+
+    /// Only display sessions started on this machine, for when several people share one account.
+    ///
+    /// A session counts as local if it connects from one of this machine's addresses,
+    /// if its device id is the one Jellyfin Media Player or jellyfin-web in a Chromium based browser
+    /// stored on this machine, or if it was passed to `ClientBuilder::local_device_ids()`.
+    ///
+    /// Loopback addresses never count, so a server behind a reverse proxy on this machine
+    /// doesn't make every session local.
+    ///
+    /// Defaults to `false`.
+    pub fn local_only(&mut self, val: bool) -> &mut Self {
+        self.local_only = val;
+        self
+    }
+
+    /// Device ids that count as this machine when `ClientBuilder::local_only()` is enabled,
+    /// they can be found on the Devices page of the Jellyfin dashboard.
+    ///
+    /// Empty by default.
+    pub fn local_device_ids(&mut self, device_ids: Vec<String>) -> &mut Self {
+        self.local_device_ids = device_ids;
+        self
+    }
+
     /// buttons to be displayed on the activity.
     /// Pass an empty `Vec::new()` to display no buttons
     ///
@@ -1179,6 +1851,51 @@ impl ClientBuilder {
         self
     }
 
+    pub fn livetv_separator<T: Into<String>>(&mut self, separator: T) -> &mut Self {
+        self.livetv_separator = separator.into();
+        self
+    }
+
+    pub fn livetv_display(&mut self, display: DisplayFormat) -> &mut Self {
+        self.livetv_display = display;
+        self
+    }
+
+    pub fn livetv_status_display_type(&mut self, status_type: StatusType) -> &mut Self {
+        self.livetv_status_display_type = status_type;
+        self
+    }
+
+    pub fn books_separator<T: Into<String>>(&mut self, separator: T) -> &mut Self {
+        self.books_separator = separator.into();
+        self
+    }
+
+    pub fn books_display(&mut self, display: DisplayFormat) -> &mut Self {
+        self.books_display = display;
+        self
+    }
+
+    pub fn books_status_display_type(&mut self, status_type: StatusType) -> &mut Self {
+        self.books_status_display_type = status_type;
+        self
+    }
+
+    pub fn audiobooks_separator<T: Into<String>>(&mut self, separator: T) -> &mut Self {
+        self.audiobooks_separator = separator.into();
+        self
+    }
+
+    pub fn audiobooks_display(&mut self, display: DisplayFormat) -> &mut Self {
+        self.audiobooks_display = display;
+        self
+    }
+
+    pub fn audiobooks_status_display_type(&mut self, status_type: StatusType) -> &mut Self {
+        self.audiobooks_status_display_type = status_type;
+        self
+    }
+
     /// Blacklist certain `MediaType`s so they don't display.
     ///
     /// Defaults to `Vec::new()`.
@@ -1228,17 +1945,9 @@ impl ClientBuilder {
     }
 
     /// Where to store the URLs to images uploaded to imgur.
-    /// Having this cache lets you avoid uploading the same image several times to their service.
-    ///
-    /// Empty by default.
-    ///
-    /// # Warning
-    /// Setting this to something like `/dev/null` is **NOT** recommended,
-    /// jellyfin-rpc will upload the image every time you call `Client::set_activity()`
-    /// if it can't find the image its looking for in the cache.
+    #[deprecated(note = "every image host shares one cache now, use `image_cache_location()`")]
     pub fn imgur_urls_file_location<T: Into<String>>(&mut self, location: T) -> &mut Self {
-        self.imgur_urls_file_location = location.into();
-        self
+        self.image_cache_location(location)
     }
 
     /// Use litterbox.catbox.moe for images, uploads images from jellyfin to litterbox and stores the litterbox links in a local cache
@@ -1250,15 +1959,57 @@ impl ClientBuilder {
     }
 
     /// Where to store the URLs to images uploaded to litterbox.
+    #[deprecated(note = "every image host shares one cache now, use `image_cache_location()`")]
+    pub fn litterbox_urls_file_location<T: Into<String>>(&mut  self, location: T) -> &mut Self {
+        self.image_cache_location(location)
+    }
+
+    /// Where to store the URLs to images uploaded to image hosts, see [`ImageCache`].
     /// Having this cache lets you avoid uploading the same image several times to their service.
     ///
-    /// Empty by default.
-    pub fn litterbox_urls_file_location<T: Into<String>>(&mut  self, location: T) -> &mut Self {
-        self.litterbox_urls_file_location = location.into();
+    /// Caches written by older versions, including the `urls.json` files of imgur and litterbox,
+    /// are migrated the first time they're read.
+    ///
+    /// Empty by default, which uploads the image every time you call `Client::set_activity()`.
+    pub fn image_cache_location<T: Into<String>>(&mut self, location: T) -> &mut Self {
+        self.image_cache_location = location.into();
+        self
+    }
+
+    /// Add a host to upload images to, see [`ImageHost`].
+    ///
+    /// Hosts are tried in the order they were added until one of them accepts the image,
+    /// imgur and litterbox come first when they're enabled.
+    pub fn image_host<H: ImageHost + 'static>(&mut self, host: H) -> &mut Self {
+        self.image_hosts.push(Arc::new(host));
+        self
+    }
+
+    /// Images to show for `media_type`, tried in order until the item has one of them.
+    ///
+    /// Defaults to [`ImageSource::defaults`], falls back to the default image when none of them exist.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::{ClientBuilder, ImageSource, MediaType};
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.image_sources(
+    ///     MediaType::Episode,
+    ///     vec![
+    ///         "thumb".parse().unwrap(),
+    ///         "season:primary".parse().unwrap(),
+    ///         "series:primary".parse().unwrap(),
+    ///     ],
+    /// );
+    /// ```
+    pub fn image_sources(&mut self, media_type: MediaType, sources: Vec<ImageSource>) -> &mut Self {
+        self.image_sources.retain(|(kind, _)| *kind != media_type);
+        self.image_sources.push((media_type, sources));
         self
     }
 
-    /// Process images before uploading to imgur or litterbox
+    /// Process images before uploading them to an image host
     ///
     /// Defaults to `true`.
     pub fn process_images(&mut self, val: bool) -> &mut Self {
@@ -1274,6 +2025,19 @@ impl ClientBuilder {
         self
     }
 
+    /// Subscribe to session updates over the Jellyfin WebSocket instead of polling `/Sessions`.
+    /// Polling is still used whenever the WebSocket is disconnected.
+    ///
+    /// Defaults to `true`.
+    ///
+    /// # Warning
+    /// The WebSocket can't be used together with `ClientBuilder::self_signed()`,
+    /// https servers with self signed certificates will always be polled.
+    pub fn websocket(&mut self, val: bool) -> &mut Self {
+        self.websocket = val;
+        self
+    }
+
     /// Builds a client from the options specified in the builder.
     ///
     /// # Example
@@ -1288,61 +2052,210 @@ impl ClientBuilder {
     /// let mut client = builder.build().unwrap();
     /// ```
     pub fn build(self) -> JfResult<Client> {
-        if self.url.is_empty() || self.usernames.is_empty() || self.api_key.is_empty() {
-            return Err(Box::new(JfError::MissingRequiredValues));
-        }
+        let client_id = self.client_id.clone();
 
-        let mut headers = HeaderMap::new();
+        let core = self.build_core()?;
 
-        headers.insert(
-            AUTHORIZATION,
-            format!("MediaBrowser Token=\"{}\"", self.api_key).parse()?,
-        );
-        headers.insert("X-Emby-Token", self.api_key.parse()?);
+        let connections = core
+            .servers
+            .iter()
+            .map(|server| {
+                Ok(Connection::new(
+                    reqwest::blocking::Client::builder()
+                        .default_headers(server.headers.clone())
+                        .danger_accept_invalid_certs(server.self_signed)
+                        .build()?,
+                ))
+            })
+            .collect::<JfResult<_>>()?;
 
         Ok(Client {
-            discord_ipc_client: DiscordIpcClient::new(&self.client_id),
-            url: self.url.parse()?,
-            reqwest: reqwest::blocking::Client::builder()
-                .default_headers(headers)
-                .danger_accept_invalid_certs(self.self_signed)
-                .build()?,
+            discord_ipc_client: DiscordIpcClient::new(&client_id),
+            connections,
+            update_signal: Arc::default(),
+            core,
+        })
+    }
+
+    /// Builds an `AsyncClient` from the options specified in the builder.
+    ///
+    /// # Example
+    /// ```
+    /// use jellyfin_rpc::ClientBuilder;
+    ///
+    /// let mut builder = ClientBuilder::new();
+    /// builder.api_key("abcd1234")
+    ///     .url("https://jellyfin.example.com")
+    ///     .username("user");
+    ///
+    /// let mut client = builder.build_async().unwrap();
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> JfResult<AsyncClient> {
+        let client_id = self.client_id.clone();
+
+        let core = self.build_core()?;
+
+        AsyncClient::new(&client_id, core)
+    }
+
+    /// Everything both clients have in common
+    fn build_core(self) -> JfResult<Core> {
+        let primary = (!self.url.is_empty()).then(|| ServerConfig {
+            url: self.url,
+            api_key: self.api_key,
+            kind: self.server_kind,
             usernames: self.usernames,
+            user_ids: self.user_ids,
+            self_signed: self.self_signed,
+            ..Default::default()
+        });
+
+        let configs: Vec<ServerConfig> = primary.into_iter().chain(self.servers).collect();
+
+        let mut image_hosts: Vec<Arc<dyn ImageHost>> = Vec::new();
+        if self.use_imgur {
+            image_hosts.push(Arc::new(Imgur::new(self.imgur_client_id)));
+        }
+        if self.use_litterbox {
+            image_hosts.push(Arc::new(Litterbox::new()));
+        }
+        image_hosts.extend(self.image_hosts);
+
+        let image_cache = if self.image_cache_location.is_empty() || image_hosts.is_empty() {
+            None
+        } else {
+            match ImageCache::open(&self.image_cache_location) {
+                Ok(cache) => Some(Arc::new(cache)),
+                Err(err) => {
+                    warn!(
+                        "Couldn't read the image cache at {}, images won't be cached: {}",
+                        self.image_cache_location, err
+                    );
+                    None
+                }
+            }
+        };
+
+        if configs.is_empty()
+            || configs.iter().any(|config| {
+                config.url.is_empty()
+                    || (config.usernames.is_empty() && config.user_ids.is_empty())
+                    || config.api_key.is_empty()
+            })
+        {
+            return Err(JfError::MissingRequiredValues);
+        }
+
+        let servers = configs
+            .into_iter()
+            .map(|config| {
+                let blacklist = Blacklist {
+                    media_types: self
+                        .blacklist_media_types
+                        .iter()
+                        .chain(&config.blacklist_media_types)
+                        .cloned()
+                        .collect(),
+                    libraries_names: self
+                        .blacklist_libraries
+                        .iter()
+                        .chain(&config.blacklist_libraries)
+                        .cloned()
+                        .collect(),
+                    libraries: BlacklistedLibraries::Uninitialized,
+                };
+
+                Server::new(config, self.websocket, self.device.as_ref(), blacklist)
+            })
+            .collect::<JfResult<_>>()?;
+
+        let core = Core {
+            servers,
             buttons: self.buttons,
             session: None,
-            music_display_options: DisplayOptions {
-                separator: self.music_separator,
-                display: self.music_display,
-                status_display_type: self.music_status_display_type,
-            },
-            movies_display_options: DisplayOptions {
-                separator: self.movies_separator,
-                display: self.movies_display,
-                status_display_type: self.movies_status_display_type,
-            },
-            episodes_display_options: DisplayOptions {
-                separator: self.episodes_separator,
-                display: self.episodes_display,
-                status_display_type: self.episodes_status_display_type,
-            },
-            blacklist: Blacklist {
-                media_types: self.blacklist_media_types,
-                libraries_names: self.blacklist_libraries,
-                libraries: BlacklistedLibraries::Uninitialized,
-            },
+            session_server: 0,
+            companion_session: None,
+            session_starts: HashMap::new(),
+            session_priority: self.session_priority,
+            combine_sessions: self.combine_sessions,
+            session_allow: self.session_allow,
+            session_deny: self.session_deny,
+            local_machine: self
+                .local_only
+                .then(|| LocalMachine::detect(self.local_device_ids)),
+            last_presence: None,
+            live_program: None,
+            music_display_options: DisplayOptions::new(
+                MediaType::Music,
+                self.music_separator,
+                self.music_display,
+                self.music_status_display_type,
+            )?,
+            movies_display_options: DisplayOptions::new(
+                MediaType::Movie,
+                self.movies_separator,
+                self.movies_display,
+                self.movies_status_display_type,
+            )?,
+            episodes_display_options: DisplayOptions::new(
+                MediaType::Episode,
+                self.episodes_separator,
+                self.episodes_display,
+                self.episodes_status_display_type,
+            )?,
+            livetv_display_options: DisplayOptions::new(
+                MediaType::LiveTv,
+                self.livetv_separator,
+                self.livetv_display,
+                self.livetv_status_display_type,
+            )?,
+            books_display_options: DisplayOptions::new(
+                MediaType::Book,
+                self.books_separator,
+                self.books_display,
+                self.books_status_display_type,
+            )?,
+            audiobooks_display_options: DisplayOptions::new(
+                MediaType::AudioBook,
+                self.audiobooks_separator,
+                self.audiobooks_display,
+                self.audiobooks_status_display_type,
+            )?,
+            builtin_display_options: [
+                MediaType::MusicVideo,
+                MediaType::Video,
+                MediaType::Trailer,
+                MediaType::Recording,
+                MediaType::Photo,
+            ]
+            .into_iter()
+            .map(|media_type| {
+                let options = DisplayOptions::new(
+                    media_type,
+                    "-".to_string(),
+                    DisplayFormat::default(),
+                    StatusType::default(),
+                )?;
+
+                Ok((media_type, options))
+            })
+            .collect::<JfResult<_>>()?,
+            generic_display_options: DisplayOptions::new(
+                MediaType::None,
+                "-".to_string(),
+                DisplayFormat::default(),
+                StatusType::default(),
+            )?,
             show_paused: self.show_paused,
             show_images: self.show_images,
-            imgur_options: ImgurOptions {
-                enabled: self.use_imgur,
-                client_id: self.imgur_client_id,
-                urls_location: self.imgur_urls_file_location,
-            },
-            litterbox_options: LitterboxOptions {
-                enabled: self.use_litterbox,
-                urls_location: self.litterbox_urls_file_location,
-            },
+            image_hosts,
+            image_cache,
+            image_sources: self.image_sources,
             process_images: self.process_images,
             large_image_text: self.large_image_text,
-        })
+        };
+
+        Ok(core)
     }
 }
@@ -0,0 +1,222 @@
+use crate::jellyfin::RawSession;
+use log::{debug, warn};
+use std::{
+    env, fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// How long the interface addresses are used before they're listed again,
+/// they only change when switching networks.
+const INTERFACES_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Key jellyfin-web stores its device id under in local storage.
+const DEVICE_ID_KEY: &[u8] = b"\x01_deviceId2";
+
+/// Identifies sessions that were started on this machine.
+///
+/// A session is local if Jellyfin sees it connecting from one of this machine's addresses,
+/// or if its device id is the one Jellyfin Media Player or jellyfin-web in a browser
+/// stored on this machine, or was configured as local.
+///
+/// Loopback addresses are left out, a server behind a reverse proxy on the same machine
+/// sees every session coming from them.
+pub(crate) struct LocalMachine {
+    addresses: Vec<IpAddr>,
+    /// When `addresses` were listed, `None` if they never were
+    listed_at: Option<Instant>,
+    device_ids: Vec<String>,
+}
+
+impl LocalMachine {
+    pub fn detect(mut device_ids: Vec<String>) -> Self {
+        for dir in storage_dirs() {
+            for device_id in read_device_ids(&dir) {
+                if !device_ids.contains(&device_id) {
+                    debug!("Found local device id {} in {}", device_id, dir.display());
+                    device_ids.push(device_id);
+                }
+            }
+        }
+
+        let mut local = Self {
+            addresses: Vec::new(),
+            listed_at: None,
+            device_ids,
+        };
+
+        local.refresh();
+        local
+    }
+
+    #[cfg(test)]
+    pub fn with_addresses(addresses: Vec<IpAddr>, device_ids: Vec<String>) -> Self {
+        Self {
+            addresses,
+            listed_at: Some(Instant::now()),
+            device_ids,
+        }
+    }
+
+    /// Lists the interface addresses again once they're older than `INTERFACES_TTL`
+    pub fn refresh(&mut self) {
+        if self
+            .listed_at
+            .is_some_and(|listed_at| listed_at.elapsed() < INTERFACES_TTL)
+        {
+            return;
+        }
+
+        match if_addrs::get_if_addrs() {
+            Ok(interfaces) => {
+                self.addresses = interfaces
+                    .iter()
+                    .filter(|i| !i.is_loopback())
+                    .map(|i| i.ip())
+                    .collect();
+                debug!("Local addresses are {:?}", self.addresses);
+            }
+            Err(err) => warn!("Failed to list network interfaces: {}", err),
+        }
+
+        self.listed_at = Some(Instant::now());
+    }
+
+    pub fn matches(&self, session: &RawSession) -> bool {
+        let address_matches = session
+            .remote_end_point
+            .as_ref()
+            .and_then(|remote| remote.parse::<IpAddr>().ok())
+            .map(|remote| remote.to_canonical())
+            .is_some_and(|remote| !remote.is_loopback() && self.addresses.contains(&remote));
+
+        let device_id_matches = session
+            .device_id
+            .as_ref()
+            .is_some_and(|device_id| self.device_ids.contains(device_id));
+
+        address_matches || device_id_matches
+    }
+}
+
+/// Local storage folders of Jellyfin Media Player and the usual Chromium based browsers
+fn storage_dirs() -> Vec<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+
+    if cfg!(windows) {
+        if let Some(local) = env::var_os("LOCALAPPDATA").map(PathBuf::from) {
+            dirs.push(local.join("JellyfinMediaPlayer/QtWebEngine/Default"));
+            dirs.push(local.join("Google/Chrome/User Data/Default"));
+            dirs.push(local.join("Microsoft/Edge/User Data/Default"));
+            dirs.push(local.join("BraveSoftware/Brave-Browser/User Data/Default"));
+        }
+    } else if cfg!(target_os = "macos") {
+        if let Some(support) = home.map(|home| home.join("Library/Application Support")) {
+            dirs.push(support.join("Jellyfin Media Player/QtWebEngine/Default"));
+            dirs.push(support.join("Google/Chrome/Default"));
+            dirs.push(support.join("Microsoft Edge/Default"));
+            dirs.push(support.join("BraveSoftware/Brave-Browser/Default"));
+        }
+    } else if let Some(home) = home {
+        let data = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".local/share"));
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".config"));
+
+        dirs.push(data.join("jellyfinmediaplayer/QtWebEngine/Default"));
+        dirs.push(home.join(
+            ".var/app/com.github.iwalton3.jellyfin-media-player/data/jellyfinmediaplayer/QtWebEngine/Default",
+        ));
+        dirs.push(config.join("google-chrome/Default"));
+        dirs.push(config.join("chromium/Default"));
+        dirs.push(config.join("microsoft-edge/Default"));
+        dirs.push(config.join("BraveSoftware/Brave-Browser/Default"));
+    }
+
+    dirs.into_iter()
+        .map(|dir| dir.join("Local Storage/leveldb"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Device ids jellyfin-web stored in a Chromium local storage folder.
+///
+/// Only the uncompressed parts of the database are searched,
+/// which is where recent writes such as the device id end up.
+pub(crate) fn read_device_ids(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut device_ids = Vec::new();
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path
+            .extension()
+            .is_some_and(|extension| extension == "log" || extension == "ldb")
+        {
+            continue;
+        }
+
+        let Ok(contents) = fs::read(&path) else {
+            continue;
+        };
+
+        for device_id in find_device_ids(&contents) {
+            if !device_ids.contains(&device_id) {
+                device_ids.push(device_id);
+            }
+        }
+    }
+
+    device_ids
+}
+
+/// Values stored under `_deviceId2`, each value is prefixed with its length
+/// and a byte that says whether it's Latin-1 or UTF-16
+fn find_device_ids(contents: &[u8]) -> Vec<String> {
+    let mut device_ids = Vec::new();
+    let mut rest = contents;
+
+    while let Some(position) = rest
+        .windows(DEVICE_ID_KEY.len())
+        .position(|window| window == DEVICE_ID_KEY)
+    {
+        rest = &rest[position + DEVICE_ID_KEY.len()..];
+
+        let Some((&length, value)) = rest.split_first() else {
+            break;
+        };
+        let length = length as usize;
+
+        // Device ids are short enough for the length to fit in a single byte
+        if !(2..=0x7f).contains(&length) || value.len() < length {
+            continue;
+        }
+
+        let device_id = match value[0] {
+            1 => String::from_utf8(value[1..length].to_vec()).ok(),
+            0 => String::from_utf16(
+                &value[1..length]
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect::<Vec<u16>>(),
+            )
+            .ok(),
+            _ => None,
+        };
+
+        if let Some(device_id) = device_id.filter(|id| {
+            id.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"+/=-_".contains(&b))
+        }) {
+            device_ids.push(device_id);
+        }
+    }
+
+    device_ids
+}
@@ -0,0 +1,404 @@
+use crate::jellyfin::{NowPlayingItem, Program, Session};
+use crate::template::Value;
+use crate::{MediaType, VERSION};
+
+/// A placeholder that can be used in the [`DisplayFormat`](crate::DisplayFormat) of a media type.
+#[derive(Debug, Clone, Copy)]
+pub struct Placeholder {
+    /// Written as `{name}` in display formats
+    pub name: &'static str,
+    pub description: &'static str,
+    /// What the placeholder could be replaced with
+    pub example: &'static str,
+    value: fn(&Session) -> Value,
+}
+
+impl Placeholder {
+    /// Value of the placeholder for `session`, empty if the item doesn't have it
+    pub(crate) fn value(&self, session: &Session) -> Value {
+        (self.value)(session)
+    }
+}
+
+/// Every placeholder that can be used in the display formats of `media_type`.
+///
+/// `{sep}` and `{raw:Field}` can be used for every media type and aren't listed.
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::{placeholders, MediaType};
+///
+/// for placeholder in placeholders(MediaType::Music) {
+///     println!("{{{}}}: {}", placeholder.name, placeholder.description);
+/// }
+/// ```
+pub fn placeholders(media_type: MediaType) -> &'static [Placeholder] {
+    match media_type {
+        MediaType::Music => MUSIC,
+        MediaType::Movie => MOVIE,
+        MediaType::Episode => EPISODE,
+        MediaType::LiveTv => LIVE_TV,
+        MediaType::Book => BOOK,
+        MediaType::AudioBook => AUDIOBOOK,
+        MediaType::MusicVideo => MUSIC_VIDEO,
+        MediaType::Video | MediaType::Photo => VIDEO,
+        MediaType::Recording => RECORDING,
+        MediaType::Trailer | MediaType::ChannelItem | MediaType::None => GENERIC,
+    }
+}
+
+fn text(value: &Option<String>) -> Value {
+    value.clone().unwrap_or_default().into()
+}
+
+fn number(value: Option<i64>) -> Value {
+    value.map(|n| n.to_string()).unwrap_or_default().into()
+}
+
+fn list(value: &Option<Vec<String>>) -> Value {
+    Value::list(value.clone().unwrap_or_default())
+}
+
+/// Field of the Live TV programme that is airing, empty if it couldn't be looked up
+fn program(session: &Session, field: fn(&Program) -> Option<String>) -> Value {
+    session
+        .now_playing_item
+        .current_program
+        .as_ref()
+        .and_then(field)
+        .unwrap_or_default()
+        .into()
+}
+
+/// Name of the folder the file is in, the server might use either kind of slash
+fn folder(item: &NowPlayingItem) -> Value {
+    item.path
+        .as_deref()
+        .and_then(|path| path.rsplit(['/', '\\']).nth(1))
+        .unwrap_or_default()
+        .to_string()
+        .into()
+}
+
+/// One episode on Jellyfin can span across multiple actual episodes,
+/// for example E01-03 is 3 episodes in one media file
+fn episode_range(item: &NowPlayingItem, padded: bool) -> Value {
+    let Some(first) = item.index_number else {
+        return Value::Text(String::new());
+    };
+
+    match (item.index_number_end, padded) {
+        (Some(last), false) => format!("{}-{}", first, last),
+        (Some(last), true) => format!("{:02}-{:02}", first, last),
+        (None, false) => first.to_string(),
+        (None, true) => format!("{:02}", first),
+    }
+    .into()
+}
+
+const TITLE: Placeholder = Placeholder {
+    name: "title",
+    description: "Title of the item",
+    example: "Pilot",
+    value: |session| session.now_playing_item.name.clone().into(),
+};
+
+const ORIGINAL_TITLE: Placeholder = Placeholder {
+    name: "original-title",
+    description: "Title in the original language, empty if it's the same",
+    example: "千と千尋の神隠し",
+    value: |session| text(&session.now_playing_item.original_title),
+};
+
+const GENRES: Placeholder = Placeholder {
+    name: "genres",
+    description: "List of genres",
+    example: "Drama, Crime",
+    value: |session| list(&session.now_playing_item.genres),
+};
+
+const YEAR: Placeholder = Placeholder {
+    name: "year",
+    description: "Year it was released",
+    example: "2008",
+    value: |session| {
+        session
+            .now_playing_item
+            .production_year
+            .map(|y| y.to_string())
+            .unwrap_or_default()
+            .into()
+    },
+};
+
+const PREMIERE_DATE: Placeholder = Placeholder {
+    name: "premiere-date",
+    description: "Date it was released, use the date filter to format it",
+    example: "2008-01-20T00:00:00.0000000Z",
+    value: |session| text(&session.now_playing_item.premiere_date),
+};
+
+const ALBUM: Placeholder = Placeholder {
+    name: "album",
+    description: "Album it belongs to",
+    example: "A Night at the Opera",
+    value: |session| text(&session.now_playing_item.album),
+};
+
+const SHOW_TITLE: Placeholder = Placeholder {
+    name: "show-title",
+    description: "Title of the show",
+    example: "Breaking Bad",
+    value: |session| text(&session.now_playing_item.series_name),
+};
+
+const VERSION_NUMBER: Placeholder = Placeholder {
+    name: "version",
+    description: "Version of Jellyfin-RPC",
+    example: "1.3.4",
+    value: |_| VERSION.unwrap_or("UNKNOWN").to_string().into(),
+};
+
+const ARTISTS: Placeholder = Placeholder {
+    name: "artists",
+    description: "List of artists or authors",
+    example: "Queen and David Bowie",
+    value: |session| Value::names(session.now_playing_item.artists.clone().unwrap_or_default()),
+};
+
+const MUSIC: &[Placeholder] = &[
+    Placeholder {
+        name: "track",
+        description: "Title of the track",
+        example: "Bohemian Rhapsody",
+        value: |session| session.now_playing_item.name.clone().into(),
+    },
+    ALBUM,
+    ARTISTS,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+const MOVIE: &[Placeholder] = &[
+    TITLE,
+    ORIGINAL_TITLE,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    Placeholder {
+        name: "critic-score",
+        description: "Critic rating out of 100",
+        example: "🍅 94/100",
+        value: |session| {
+            session
+                .now_playing_item
+                .critic_rating
+                .map(|s| format!("🍅 {}/100", s))
+                .unwrap_or_default()
+                .into()
+        },
+    },
+    Placeholder {
+        name: "community-score",
+        description: "Community rating out of 10",
+        example: "⭐ 8.6/10",
+        value: |session| {
+            session
+                .now_playing_item
+                .community_rating
+                .map(|s| format!("⭐ {:.1}/10", s))
+                .unwrap_or_default()
+                .into()
+        },
+    },
+    VERSION_NUMBER,
+];
+
+const EPISODE: &[Placeholder] = &[
+    SHOW_TITLE,
+    TITLE,
+    ORIGINAL_TITLE,
+    Placeholder {
+        name: "episode",
+        description: "Episode number, or a range for files with several episodes",
+        example: "1-3",
+        value: |session| episode_range(&session.now_playing_item, false),
+    },
+    Placeholder {
+        name: "episode-padded",
+        description: "Episode number padded to two digits",
+        example: "01-03",
+        value: |session| episode_range(&session.now_playing_item, true),
+    },
+    Placeholder {
+        name: "season",
+        description: "Season number",
+        example: "1",
+        value: |session| {
+            session
+                .now_playing_item
+                .parent_index_number
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+                .into()
+        },
+    },
+    Placeholder {
+        name: "season-padded",
+        description: "Season number padded to two digits",
+        example: "01",
+        value: |session| {
+            session
+                .now_playing_item
+                .parent_index_number
+                .map(|s| format!("{:02}", s))
+                .unwrap_or_default()
+                .into()
+        },
+    },
+    YEAR,
+    PREMIERE_DATE,
+    GENRES,
+    Placeholder {
+        name: "studio",
+        description: "Studio of the show",
+        example: "AMC",
+        value: |session| text(&session.now_playing_item.series_studio),
+    },
+    VERSION_NUMBER,
+];
+
+const LIVE_TV: &[Placeholder] = &[
+    Placeholder {
+        name: "title",
+        description: "Name of the channel",
+        example: "BBC One",
+        value: |session| session.now_playing_item.name.clone().into(),
+    },
+    Placeholder {
+        name: "channel",
+        description: "Name of the channel",
+        example: "BBC One",
+        value: |session| session.now_playing_item.name.clone().into(),
+    },
+    Placeholder {
+        name: "channel-number",
+        description: "Number of the channel",
+        example: "101",
+        value: |session| {
+            let item = &session.now_playing_item;
+            item.number
+                .clone()
+                .or_else(|| item.current_program.as_ref()?.channel_number.clone())
+                .unwrap_or_default()
+                .into()
+        },
+    },
+    Placeholder {
+        name: "program",
+        description: "Name of the programme that is airing",
+        example: "Doctor Who",
+        value: |session| program(session, |p| Some(p.name.clone())),
+    },
+    Placeholder {
+        name: "episode-title",
+        description: "Episode title of the programme that is airing",
+        example: "The Eleventh Hour",
+        value: |session| program(session, |p| p.episode_title.clone()),
+    },
+    GENRES,
+    VERSION_NUMBER,
+];
+
+const BOOK: &[Placeholder] = &[
+    TITLE,
+    Placeholder {
+        name: "page",
+        description: "Page that is being read, empty for EPUBs",
+        example: "42",
+        value: |session| number(session.book_progress().page),
+    },
+    Placeholder {
+        name: "page-count",
+        description: "Number of pages, empty if the server doesn't know it",
+        example: "310",
+        value: |session| number(session.book_progress().page_count),
+    },
+    Placeholder {
+        name: "percent",
+        description: "How much of the book has been read, without the percent sign",
+        example: "42",
+        value: |session| number(session.book_progress().percent),
+    },
+    Placeholder {
+        name: "chapter",
+        description: "Chapter that is being read, empty if the book has no chapters",
+        example: "Chapter 7",
+        value: |session| text(&session.book_progress().chapter),
+    },
+    Placeholder {
+        name: "author",
+        description: "List of authors",
+        example: "J. R. R. Tolkien",
+        value: |session| Value::names(session.authors()),
+    },
+    ARTISTS,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+const AUDIOBOOK: &[Placeholder] = &[
+    TITLE,
+    Placeholder {
+        name: "album",
+        description: "Name of the audiobook the chapter belongs to",
+        example: "The Hobbit",
+        value: |session| text(&session.now_playing_item.album),
+    },
+    ARTISTS,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+const MUSIC_VIDEO: &[Placeholder] = &[
+    TITLE,
+    ARTISTS,
+    ALBUM,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+const VIDEO: &[Placeholder] = &[
+    TITLE,
+    ALBUM,
+    Placeholder {
+        name: "folder",
+        description: "Name of the folder the file is in",
+        example: "Holiday 2019",
+        value: |session| folder(&session.now_playing_item),
+    },
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+const RECORDING: &[Placeholder] = &[
+    TITLE,
+    SHOW_TITLE,
+    GENRES,
+    YEAR,
+    PREMIERE_DATE,
+    VERSION_NUMBER,
+];
+
+/// Used for trailers, channel items and anything that isn't recognized
+const GENERIC: &[Placeholder] = &[TITLE, GENRES, YEAR, PREMIERE_DATE, VERSION_NUMBER];
@@ -0,0 +1,380 @@
+use crate::auth::{self, Device};
+use crate::jellyfin::{RawSession, SystemInfo, User, VirtualFolder};
+use crate::socket::{self, SessionSocket, UpdateSignal};
+use crate::{Blacklist, BlacklistedLibraries, JfResult, MediaType, Problem, ServerReport};
+use log::{debug, warn};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use url::Url;
+
+/// Sessions that haven't been active for this long are left out by Jellyfin.
+const ACTIVE_WITHIN_SECONDS: u32 = 960;
+
+/// How long usernames are matched by name after the user list couldn't be read,
+/// before the ids are looked up again.
+const USERS_RETRY: Duration = Duration::from_secs(30 * 60);
+
+/// Identifies the client to Emby, which requires a device id on the websocket,
+/// unless one was set with `ClientBuilder::device()`.
+const EMBY_DEVICE_ID: &str = "jellyfin-rpc";
+
+/// The kind of media server, Emby shares most of its API with Jellyfin but not all of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerKind {
+    #[default]
+    Jellyfin,
+    Emby,
+}
+
+impl ServerKind {
+    /// Base of every API path, Emby serves its API under `emby/`
+    pub(crate) fn api_url(self, url: Url) -> JfResult<Url> {
+        Ok(match self {
+            ServerKind::Emby if !url.path().ends_with("/emby/") => url.join("emby/")?,
+            _ => url,
+        })
+    }
+}
+
+/// Parses a server url, adding the trailing `/` that's needed to join API paths onto it
+pub(crate) fn base_url(url: &str) -> JfResult<Url> {
+    let mut url: Url = url.parse()?;
+
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(url)
+}
+
+/// A Jellyfin server to look for sessions on, used with `ClientBuilder::servers()`
+///
+/// # Example
+/// ```
+/// use jellyfin_rpc::ServerConfig;
+///
+/// let server = ServerConfig {
+///     url: "https://jellyfin.example.com".to_string(),
+///     api_key: "abcd1234".to_string(),
+///     usernames: vec!["user".to_string()],
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ServerConfig {
+    pub url: String,
+    pub api_key: String,
+    /// Whether this is a Jellyfin or an Emby server, see `ClientBuilder::server_kind()`
+    pub kind: ServerKind,
+    /// Usernames that should be matched when checking sessions on this server
+    pub usernames: Vec<String>,
+    /// User ids that should be matched when checking sessions on this server
+    pub user_ids: Vec<String>,
+    /// Accept self signed certificates, see `ClientBuilder::self_signed()`
+    pub self_signed: bool,
+    /// Blacklisted media types on this server, on top of `ClientBuilder::blacklist_media_types()`
+    pub blacklist_media_types: Vec<MediaType>,
+    /// Blacklisted libraries on this server, on top of `ClientBuilder::blacklist_libraries()`
+    pub blacklist_libraries: Vec<String>,
+}
+
+/// Options and state of a single server.
+pub(crate) struct Server {
+    pub kind: ServerKind,
+    /// Base of every API path, `emby/` is appended to the configured url on Emby
+    pub url: Url,
+    /// Authentication headers sent with every request
+    pub headers: HeaderMap,
+    pub self_signed: bool,
+    pub socket_url: Option<Url>,
+    usernames: Vec<String>,
+    user_ids: Vec<String>,
+    /// Ids of `usernames`, `None` until they've been looked up on Jellyfin
+    resolved_user_ids: Option<Vec<String>>,
+    /// When looking up `usernames` last failed, usually because the token isn't an admin's
+    users_failed: Option<Instant>,
+    pub blacklist: Blacklist,
+}
+
+impl Server {
+    pub fn new(
+        config: ServerConfig,
+        websocket: bool,
+        device: Option<&Device>,
+        blacklist: Blacklist,
+    ) -> JfResult<Self> {
+        let headers = auth::headers(config.kind, Some(&config.api_key), device)?;
+
+        let base_url = base_url(&config.url)?;
+
+        let socket_url = if !websocket {
+            None
+        } else if config.self_signed && base_url.scheme() == "https" {
+            debug!("Self signed certificates are not supported by the websocket, polling instead");
+            None
+        } else {
+            match config.kind {
+                ServerKind::Jellyfin => socket::socket_url(&base_url, "socket"),
+                ServerKind::Emby => {
+                    socket::socket_url(&base_url, "embywebsocket").map(|mut socket_url| {
+                        socket_url
+                            .query_pairs_mut()
+                            .append_pair("deviceId", device.map_or(EMBY_DEVICE_ID, |d| &d.id));
+                        socket_url
+                    })
+                }
+            }
+        };
+
+        Ok(Self {
+            kind: config.kind,
+            url: config.kind.api_url(base_url)?,
+            headers,
+            self_signed: config.self_signed,
+            socket_url,
+            usernames: config.usernames,
+            user_ids: config.user_ids,
+            resolved_user_ids: None,
+            users_failed: None,
+            blacklist,
+        })
+    }
+
+    /// Url of the user list, Emby only returns it from the query endpoint
+    pub fn users_url(&self) -> JfResult<Url> {
+        Ok(match self.kind {
+            ServerKind::Jellyfin => self.url.join("Users")?,
+            ServerKind::Emby => self.url.join("Users/Query")?,
+        })
+    }
+
+    /// Url of the programme airing on a Live TV channel right now
+    pub fn program_url(&self, channel_id: &str) -> JfResult<Url> {
+        let mut url = self.url.join("LiveTv/Programs")?;
+        url.query_pairs_mut()
+            .append_pair("ChannelIds", channel_id)
+            .append_pair("IsAiring", "true")
+            .append_pair("Limit", "1");
+
+        Ok(url)
+    }
+
+    /// Url of the server info, only readable with valid credentials
+    pub fn system_info_url(&self) -> JfResult<Url> {
+        Ok(self.url.join("System/Info")?)
+    }
+
+    /// Checks the server info and user list against the configured users,
+    /// `users` is ignored if the server info couldn't be read.
+    pub fn report(&self, info: JfResult<SystemInfo>, users: JfResult<Vec<User>>) -> ServerReport {
+        let mut report = ServerReport {
+            url: self.url.clone(),
+            server_name: None,
+            product_name: None,
+            version: None,
+            problems: Vec::new(),
+        };
+
+        match info {
+            Ok(info) => {
+                report.server_name = info.server_name;
+                report.product_name = info.product_name;
+                report.version = info.version;
+            }
+            Err(err) => {
+                report.problems.push(err.into());
+                return report;
+            }
+        }
+
+        let users = match users {
+            Ok(users) => users,
+            Err(err) => {
+                report
+                    .problems
+                    .push(Problem::UsersNotListed(err.to_string()));
+                return report;
+            }
+        };
+
+        for username in &self.usernames {
+            if !users
+                .iter()
+                .any(|user| user.name.to_lowercase() == username.to_lowercase())
+            {
+                report
+                    .problems
+                    .push(Problem::UnknownUsername(username.clone()));
+            }
+        }
+
+        for id in &self.user_ids {
+            if !users.iter().any(|user| &user.id == id) {
+                report.problems.push(Problem::UnknownUserId(id.clone()));
+            }
+        }
+
+        report
+    }
+
+    /// Whether the session belongs to one of the configured users
+    pub fn matches_user(&self, session: &RawSession) -> bool {
+        let id_matches = session
+            .user_id
+            .as_ref()
+            .is_some_and(|id| self.user_ids().any(|u| u == id));
+
+        let name_matches = session.user_name.as_ref().is_some_and(|username| {
+            self.usernames
+                .iter()
+                .any(|u| username.to_lowercase() == u.to_lowercase())
+        });
+
+        id_matches || name_matches
+    }
+
+    /// Whether the configured usernames have been looked up already, or don't need to be.
+    ///
+    /// After a failed lookup this stays `true` for a while so the user list isn't requested every poll.
+    pub fn users_resolved(&self) -> bool {
+        self.usernames.is_empty()
+            || self.resolved_user_ids.is_some()
+            || self
+                .users_failed
+                .is_some_and(|failed| failed.elapsed() < USERS_RETRY)
+    }
+
+    /// Look up the ids of the configured usernames in the user list
+    pub fn resolve_users(&mut self, users: JfResult<Vec<User>>) {
+        match users {
+            Ok(users) => {
+                let ids: Vec<String> = users
+                    .into_iter()
+                    .filter(|user| {
+                        self.usernames
+                            .iter()
+                            .any(|u| u.to_lowercase() == user.name.to_lowercase())
+                    })
+                    .map(|user| user.id)
+                    .collect();
+
+                debug!("Resolved usernames to user ids {:?}", ids);
+                self.resolved_user_ids = Some(ids);
+            }
+            Err(err) => {
+                if self.users_failed.is_none() {
+                    warn!("Failed to look up user ids, matching on usernames: {}", err);
+                } else {
+                    debug!("Failed to look up user ids again: {}", err);
+                }
+
+                self.users_failed = Some(Instant::now());
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub fn resolved_user_ids(&self) -> Option<&Vec<String>> {
+        self.resolved_user_ids.as_ref()
+    }
+
+    /// Configured user ids along with the ones looked up from the usernames
+    fn user_ids(&self) -> impl Iterator<Item = &String> {
+        self.user_ids
+            .iter()
+            .chain(self.resolved_user_ids.iter().flatten())
+    }
+
+    /// Url to query the recently active sessions from.
+    ///
+    /// Jellyfin can't filter sessions by user, `ControllableByUserId` returns every user's sessions
+    /// to admins and leaves out the ones that can't be remote controlled.
+    /// The sessions are matched with `matches_user()` instead, on user id once it's known.
+    pub fn sessions_url(&self) -> JfResult<Url> {
+        let mut url = self.url.join("Sessions")?;
+        url.query_pairs_mut()
+            .append_pair("ActiveWithinSeconds", &ACTIVE_WITHIN_SECONDS.to_string());
+
+        Ok(url)
+    }
+
+    /// Whether the blacklisted libraries have to be (re)loaded
+    pub fn blacklist_expired(&self) -> bool {
+        match &self.blacklist.libraries {
+            BlacklistedLibraries::Uninitialized => true,
+            BlacklistedLibraries::Initialized(_, init_time) => {
+                let expired = SystemTime::now()
+                    .duration_since(*init_time)
+                    .map(|passed| passed.as_secs() > 3600)
+                    .unwrap_or(false);
+
+                if expired {
+                    debug!("reloading blacklist after cache expiration");
+                }
+
+                expired
+            }
+        }
+    }
+
+    /// Filter the virtual folder list down to the blacklisted libraries and cache it
+    pub fn set_blacklist(&mut self, virtual_folders: JfResult<Vec<VirtualFolder>>) {
+        self.blacklist.libraries = match virtual_folders {
+            Ok(virtual_folders) => BlacklistedLibraries::Initialized(
+                virtual_folders
+                    .into_iter()
+                    .filter(|library_folder| {
+                        self.blacklist
+                            .libraries_names
+                            .contains(library_folder.name.as_ref().unwrap_or(&String::new()))
+                    })
+                    .collect(),
+                SystemTime::now(),
+            ),
+            Err(err) => {
+                warn!("Failed to intialize blacklist: {}", err);
+                BlacklistedLibraries::Uninitialized
+            }
+        }
+    }
+}
+
+/// Http client and websocket subscription of a single server, `C` is the blocking or async reqwest client.
+pub(crate) struct Connection<C> {
+    pub reqwest: C,
+    session_socket: Option<SessionSocket>,
+}
+
+impl<C> Connection<C> {
+    pub fn new(reqwest: C) -> Self {
+        Self {
+            reqwest,
+            session_socket: None,
+        }
+    }
+
+    /// Starts the WebSocket subscription on first use
+    pub fn session_socket(
+        &mut self,
+        server: &Server,
+        signal: &Arc<UpdateSignal>,
+    ) -> Option<&SessionSocket> {
+        if self.session_socket.is_none() {
+            let socket_url = server.socket_url.clone()?;
+            debug!(
+                "Subscribing to session updates over the websocket of {}",
+                server.url
+            );
+            self.session_socket = Some(SessionSocket::spawn(
+                socket_url,
+                server.headers.clone(),
+                signal.clone(),
+            ));
+        }
+
+        self.session_socket.as_ref()
+    }
+}
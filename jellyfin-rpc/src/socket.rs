@@ -0,0 +1,251 @@
+use crate::jellyfin::RawSession;
+use log::{debug, warn};
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use std::{
+    io::ErrorKind,
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use tungstenite::{client::IntoClientRequest, stream::MaybeTlsStream, Message, WebSocket};
+use url::Url;
+
+/// Asks Jellyfin to push the session list right away and then every 1500ms while it changes.
+const SESSIONS_START: &str = r#"{"MessageType":"SessionsStart","Data":"0,1500"}"#;
+const KEEP_ALIVE: &str = r#"{"MessageType":"KeepAlive"}"#;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SocketMessage {
+    message_type: String,
+    data: Option<serde_json::Value>,
+}
+
+/// Last session list pushed by the server and when it was received.
+type SocketState = Option<(Vec<RawSession>, Instant)>;
+
+/// Wakes up the client when any of its sockets has something new.
+#[derive(Default)]
+pub(crate) struct UpdateSignal {
+    /// Set when something happened that the client hasn't been woken up for yet.
+    changed: Mutex<bool>,
+    condvar: Condvar,
+}
+
+impl UpdateSignal {
+    fn notify(&self) {
+        *self.changed.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        self.condvar.notify_all();
+    }
+
+    /// Blocks until a socket reports a change or `timeout` passes.
+    ///
+    /// Returns `true` if woken up by a change.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let changed = self.changed.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let (mut changed, _) = self
+            .condvar
+            .wait_timeout_while(changed, timeout, |changed| !*changed)
+            .unwrap_or_else(PoisonError::into_inner);
+
+        std::mem::take(&mut *changed)
+    }
+}
+
+/// Background subscription to the Jellyfin `/socket` endpoint.
+///
+/// The socket is read on its own thread, which keeps the latest session list around
+/// and reconnects with a backoff whenever the connection drops.
+/// While disconnected [`SessionSocket::sessions`] returns `None` so the client can fall back to polling.
+pub(crate) struct SessionSocket {
+    state: Arc<Mutex<SocketState>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SessionSocket {
+    pub fn spawn(url: Url, headers: HeaderMap, signal: Arc<UpdateSignal>) -> Self {
+        let state = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_state = state.clone();
+        let thread_stop = stop.clone();
+
+        thread::spawn(move || {
+            let mut delay = Duration::from_secs(1);
+
+            while !thread_stop.load(Ordering::Relaxed) {
+                match run(&url, &headers, &thread_state, &signal, &thread_stop) {
+                    Ok(()) => {
+                        debug!("Jellyfin websocket closed");
+                        delay = Duration::from_secs(1);
+                    }
+                    Err(err) => warn!("Jellyfin websocket error: {}", err),
+                }
+
+                *lock(&thread_state) = None;
+                signal.notify();
+
+                let until = Instant::now() + delay;
+                while Instant::now() < until && !thread_stop.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(250));
+                }
+                delay = (delay * 2).min(Duration::from_secs(60));
+            }
+        });
+
+        Self { state, stop }
+    }
+
+    /// Returns the latest session list pushed by the server.
+    ///
+    /// Playback positions are moved forward by the time passed since the list was received,
+    /// so timestamps don't drift while nothing new is pushed.
+    pub fn sessions(&self) -> Option<Vec<RawSession>> {
+        let state = lock(&self.state);
+        let (sessions, received) = state.as_ref()?;
+
+        let elapsed_ticks = (received.elapsed().as_nanos() / 100) as i64;
+
+        Some(
+            sessions
+                .iter()
+                .cloned()
+                .map(|mut session| {
+                    if let Some(play_state) = session.play_state.as_mut() {
+                        if !play_state.is_paused {
+                            play_state.position_ticks =
+                                play_state.position_ticks.map(|p| p + elapsed_ticks);
+                        }
+                    }
+                    session
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Drop for SessionSocket {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Converts the Jellyfin base url into the websocket url, `http` becomes `ws` and `https` becomes `wss`.
+///
+/// The token isn't part of the url, it's sent in the same headers as every other request.
+/// Returns `None` for any other scheme.
+pub(crate) fn socket_url(url: &Url, path: &str) -> Option<Url> {
+    let mut socket_url = url.join(path).ok()?;
+
+    let scheme = match socket_url.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        _ => return None,
+    };
+
+    socket_url.set_scheme(scheme).ok()?;
+
+    Some(socket_url)
+}
+
+fn lock(mutex: &Mutex<SocketState>) -> MutexGuard<'_, SocketState> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn run(
+    url: &Url,
+    headers: &HeaderMap,
+    state: &Mutex<SocketState>,
+    signal: &UpdateSignal,
+    stop: &AtomicBool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut request = url.as_str().into_client_request()?;
+    request.headers_mut().extend(headers.clone());
+
+    let (mut socket, _) = tungstenite::connect(request)?;
+    debug!("Connected to the Jellyfin websocket");
+
+    // Reads time out every second so keep alives can be sent and `stop` gets noticed
+    set_read_timeout(&socket, Duration::from_secs(1))?;
+
+    socket.send(Message::text(SESSIONS_START))?;
+
+    let mut keep_alive_interval: Option<Duration> = None;
+    let mut last_keep_alive = Instant::now();
+
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            let _ = socket.close(None);
+            return Ok(());
+        }
+
+        if keep_alive_interval.is_some_and(|interval| last_keep_alive.elapsed() >= interval) {
+            socket.send(Message::text(KEEP_ALIVE))?;
+            last_keep_alive = Instant::now();
+        }
+
+        let text = match socket.read() {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                continue
+            }
+            Err(err) => return Err(Box::new(err)),
+        };
+
+        let message: SocketMessage = match serde_json::from_str(&text) {
+            Ok(message) => message,
+            Err(err) => {
+                debug!("Ignoring websocket message: {}", err);
+                continue;
+            }
+        };
+
+        match message.message_type.as_str() {
+            "Sessions" => {
+                let sessions: Vec<RawSession> =
+                    serde_json::from_value(message.data.unwrap_or_default())?;
+                debug!("Websocket pushed {} sessions", sessions.len());
+
+                *lock(state) = Some((sessions, Instant::now()));
+                signal.notify();
+            }
+            "ForceKeepAlive" => {
+                // Data is the timeout in seconds, the server drops the connection if nothing arrives before then
+                let timeout = message.data.and_then(|d| d.as_u64()).unwrap_or(60);
+                keep_alive_interval = Some(Duration::from_secs((timeout / 2).max(1)));
+
+                socket.send(Message::text(KEEP_ALIVE))?;
+                last_keep_alive = Instant::now();
+            }
+            "PlaybackStart" | "PlaybackStopped" | "PlaybackProgress" => {
+                // The pushed list is outdated until the next Sessions message arrives
+                debug!("Websocket reported {}", message.message_type);
+
+                *lock(state) = None;
+                signal.notify();
+            }
+            other => debug!("Ignoring websocket message of type {}", other),
+        }
+    }
+}
+
+fn set_read_timeout(
+    socket: &WebSocket<MaybeTlsStream<TcpStream>>,
+    timeout: Duration,
+) -> std::io::Result<()> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        MaybeTlsStream::Rustls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,488 @@
+use crate::{JfError, JfResult, Placeholder};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::str::CharIndices;
+
+/// Stands in for `{sep}` while rendering so separators can be tidied up afterwards.
+const SEPARATOR: char = '\u{e000}';
+
+/// Names of the filters, anything else after a `|` is a fallback placeholder.
+const FILTERS: &[&str] = &["first", "truncate", "upper", "lower", "pad", "date"];
+
+/// Value of a placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Text(String),
+    /// Joined with `, `, or with ` and ` before the last item if `and` is set
+    List {
+        items: Vec<String>,
+        and: bool,
+    },
+}
+
+impl Value {
+    /// A list joined like `Drama, Crime`, empty items are left out
+    pub fn list(items: Vec<String>) -> Self {
+        Value::List {
+            items: items.into_iter().filter(|i| !i.is_empty()).collect(),
+            and: false,
+        }
+    }
+
+    /// A list of names joined like `Alice, Bob and Carol`, empty items are left out
+    pub fn names(items: Vec<String>) -> Self {
+        Value::List {
+            items: items.into_iter().filter(|i| !i.is_empty()).collect(),
+            and: true,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::Text(text) => text.is_empty(),
+            Value::List { items, .. } => items.is_empty(),
+        }
+    }
+
+    fn map<F: Fn(&str) -> String>(&self, f: F) -> Self {
+        match self {
+            Value::Text(text) => Value::Text(f(text)),
+            Value::List { items, and } => Value::List {
+                items: items.iter().map(|i| f(i)).collect(),
+                and: *and,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{}", text),
+            Value::List { items, and: false } => write!(f, "{}", items.join(", ")),
+            Value::List { items, and: true } => match items.split_last() {
+                Some((last, rest)) if !rest.is_empty() => {
+                    write!(f, "{} and {}", rest.join(", "), last)
+                }
+                _ => write!(f, "{}", items.join(", ")),
+            },
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Self {
+        Value::Text(text)
+    }
+}
+
+/// Everything the placeholders of a template can be filled in with.
+pub(crate) struct Values {
+    pub named: HashMap<&'static str, Value>,
+    /// The item as Jellyfin sent it, `{raw:Field}` placeholders are looked up in it
+    pub raw: serde_json::Value,
+}
+
+impl Values {
+    fn get(&self, name: &str) -> Option<Value> {
+        match name.strip_prefix("raw:") {
+            Some(path) => raw_value(&self.raw, path),
+            None => self.named.get(name).cloned(),
+        }
+    }
+}
+
+/// Follows a path like `UserData.PlayCount` through the fields of an item.
+///
+/// Strings, numbers and booleans are shown as they are, arrays become lists.
+/// A number selects an item out of an array, any other name is looked up in every item of it.
+/// Objects are shown by their `Name` field if they have one, everything else counts as empty.
+fn raw_value(item: &serde_json::Value, path: &str) -> Option<Value> {
+    let mut current = vec![item];
+
+    for segment in path.split('.') {
+        current = current
+            .into_iter()
+            .flat_map(|value| raw_child(value, segment))
+            .collect();
+    }
+
+    let mut items: Vec<String> = Vec::new();
+    let mut list = false;
+
+    for value in current {
+        match value {
+            serde_json::Value::Array(array) => {
+                list = true;
+                items.extend(array.iter().filter_map(raw_text));
+            }
+            value => items.extend(raw_text(value)),
+        }
+    }
+
+    match items.len() {
+        0 => None,
+        1 if !list => items.pop().map(Value::Text),
+        _ => Some(Value::list(items)),
+    }
+}
+
+fn raw_child<'a>(value: &'a serde_json::Value, segment: &str) -> Vec<&'a serde_json::Value> {
+    match value {
+        serde_json::Value::Object(object) => object.get(segment).into_iter().collect(),
+        serde_json::Value::Array(array) => match segment.parse::<usize>() {
+            Ok(index) => array.get(index).into_iter().collect(),
+            Err(_) => array
+                .iter()
+                .flat_map(|item| raw_child(item, segment))
+                .collect(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn raw_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(boolean) => Some(boolean.to_string()),
+        serde_json::Value::Object(object) => object.get("Name").and_then(raw_text),
+        _ => None,
+    }
+}
+
+/// Transforms the value of a placeholder, written as `{name|filter:argument}`.
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// `first:2`, keeps the first items of a list
+    First(usize),
+    /// `truncate:40`, shortens the value to at most this many characters, ending in `…`
+    Truncate(usize),
+    Upper,
+    Lower,
+    /// `pad:2`, pads the numbers in the value with zeroes
+    Pad(usize),
+    /// `date:%Y`, formats a date with strftime specifiers
+    Date(String),
+}
+
+impl Filter {
+    fn parse(name: &str, argument: Option<&str>) -> Result<Self, String> {
+        let number = || {
+            argument
+                .and_then(|a| a.trim().parse().ok())
+                .ok_or_else(|| format!("`{}` needs a number, e.g. `{}:2`", name, name))
+        };
+
+        match (name, argument) {
+            ("first", _) => Ok(Filter::First(number()?)),
+            ("truncate", _) => Ok(Filter::Truncate(number()?)),
+            ("pad", _) => Ok(Filter::Pad(number()?)),
+            ("upper", None) => Ok(Filter::Upper),
+            ("lower", None) => Ok(Filter::Lower),
+            ("upper" | "lower", Some(_)) => Err(format!("`{}` doesn't take an argument", name)),
+            ("date", Some(format)) if !format.is_empty() => {
+                if StrftimeItems::new(format).any(|item| item == Item::Error) {
+                    Err(format!("invalid date format `{}`", format))
+                } else {
+                    Ok(Filter::Date(format.to_string()))
+                }
+            }
+            ("date", _) => Err("`date` needs a format, e.g. `date:%Y`".to_string()),
+            _ => Err(format!("unknown filter `{}`", name)),
+        }
+    }
+
+    fn apply(&self, value: Value) -> Value {
+        match self {
+            Filter::First(count) => match value {
+                Value::List { items, and } => Value::List {
+                    items: items.into_iter().take(*count).collect(),
+                    and,
+                },
+                text => text,
+            },
+            Filter::Truncate(length) => {
+                let text = value.to_string();
+
+                if text.chars().count() <= *length {
+                    Value::Text(text)
+                } else if *length == 0 {
+                    Value::Text(String::new())
+                } else {
+                    let truncated: String = text.chars().take(length - 1).collect();
+                    Value::Text(format!("{}…", truncated.trim_end()))
+                }
+            }
+            Filter::Upper => value.map(str::to_uppercase),
+            Filter::Lower => value.map(str::to_lowercase),
+            Filter::Pad(width) => value.map(|text| pad_numbers(text, *width)),
+            Filter::Date(format) => value.map(|text| format_date(text, format)),
+        }
+    }
+}
+
+fn pad_numbers(text: &str, width: usize) -> String {
+    let mut result = String::new();
+    let mut digits = String::new();
+
+    for c in text.chars().chain(std::iter::once('\0')) {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        if !digits.is_empty() {
+            result += &format!("{:0>width$}", digits, width = width);
+            digits.clear();
+        }
+
+        if c != '\0' {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Formats a date like `2008-01-20T00:00:00.0000000Z`, anything that isn't a date is kept as is.
+fn format_date(text: &str, format: &str) -> String {
+    let date: Option<DateTime<FixedOffset>> = DateTime::parse_from_rfc3339(text)
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|date| date.and_utc().fixed_offset())
+        })
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|date| date.and_utc().fixed_offset())
+        });
+
+    let mut formatted = String::new();
+
+    match date {
+        Some(date) if write!(formatted, "{}", date.format(format)).is_ok() => formatted,
+        _ => text.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    /// `{sep}`
+    Separator,
+    /// `{a|b|filter}`, renders the first of the placeholders that isn't empty
+    Placeholder {
+        names: Vec<String>,
+        filters: Vec<Filter>,
+    },
+    /// `[...]`, left out if one of the placeholders directly inside it is empty
+    Section(Vec<Node>),
+}
+
+/// A parsed display format.
+///
+/// Placeholders are written as `{name}` or `{raw:Field}`, `{a|b}` falls back to `b` if `a` is empty,
+/// `{a|upper}` passes the value through a filter
+/// and `[...]` sections disappear when one of their placeholders is empty.
+/// `\` escapes the next character, e.g. `\[`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    /// Parses `input`, only the placeholders in `placeholders`, `{raw:Field}` and `{sep}` are accepted.
+    pub fn parse(input: &str, placeholders: &[Placeholder]) -> JfResult<Self> {
+        let mut parser = Parser {
+            input,
+            placeholders,
+            chars: input.char_indices(),
+        };
+
+        Ok(Self {
+            nodes: parser.nodes(None)?,
+        })
+    }
+
+    /// Fills in the placeholders, missing values count as empty.
+    pub fn render(&self, values: &Values, separator: &str) -> String {
+        let mut rendered = String::new();
+        render_nodes(&self.nodes, values, &mut rendered);
+
+        tidy(&rendered).replace(SEPARATOR, separator)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    placeholders: &'a [Placeholder],
+    chars: CharIndices<'a>,
+}
+
+impl Parser<'_> {
+    fn error<T>(&self, position: usize, reason: String) -> JfResult<T> {
+        Err(JfError::InvalidDisplayFormat {
+            format: self.input.to_string(),
+            position: self.input[..position].chars().count(),
+            reason,
+        })
+    }
+
+    /// Parses until the end of the input, or the `]` closing the section opened at `section_start`
+    fn nodes(&mut self, section_start: Option<usize>) -> JfResult<Vec<Node>> {
+        let mut nodes = Vec::new();
+
+        while let Some((position, c)) = self.chars.next() {
+            match c {
+                '\\' => match self.chars.next() {
+                    Some((_, escaped)) => push_text(&mut nodes, escaped),
+                    None => return self.error(position, "nothing to escape after `\\`".into()),
+                },
+                '{' => nodes.push(self.placeholder(position)?),
+                '}' => return self.error(position, "`}` without a matching `{`".into()),
+                '[' => nodes.push(Node::Section(self.nodes(Some(position))?)),
+                ']' if section_start.is_some() => return Ok(nodes),
+                ']' => return self.error(position, "`]` without a matching `[`".into()),
+                c => push_text(&mut nodes, c),
+            }
+        }
+
+        match section_start {
+            Some(start) => self.error(start, "`[` is never closed".into()),
+            None => Ok(nodes),
+        }
+    }
+
+    fn placeholder(&mut self, start: usize) -> JfResult<Node> {
+        let mut content = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some((_, '}')) => break,
+                Some((_, '{' | '[' | ']')) | None => {
+                    return self.error(start, "`{` is never closed".into())
+                }
+                Some((_, c)) => content.push(c),
+            }
+        }
+
+        if content.trim() == "sep" {
+            return Ok(Node::Separator);
+        }
+
+        let mut names = Vec::new();
+        let mut filters = Vec::new();
+
+        for part in content.split('|') {
+            let (name, argument) = match part.split_once(':') {
+                Some((name, argument)) => (name.trim(), Some(argument)),
+                None => (part.trim(), None),
+            };
+
+            if name.is_empty() {
+                return self.error(start, "empty placeholder".into());
+            }
+
+            if name == "raw" {
+                let path = argument.map(str::trim).unwrap_or_default();
+
+                if path.is_empty() || path.split('.').any(str::is_empty) {
+                    return self.error(
+                        start,
+                        "`raw` needs a field name, e.g. `raw:OfficialRating`".into(),
+                    );
+                } else if !filters.is_empty() {
+                    return self.error(start, format!("placeholder `raw:{}` after a filter", path));
+                }
+
+                names.push(format!("raw:{}", path));
+            } else if FILTERS.contains(&name) || argument.is_some() {
+                match Filter::parse(name, argument) {
+                    Ok(filter) => filters.push(filter),
+                    Err(reason) => return self.error(start, reason),
+                }
+            } else if !filters.is_empty() {
+                return self.error(start, format!("placeholder `{}` after a filter", name));
+            } else if !self.placeholders.iter().any(|p| p.name == name) {
+                return self.error(start, format!("unknown placeholder `{}`", name));
+            } else {
+                names.push(name.to_string());
+            }
+        }
+
+        if names.is_empty() {
+            return self.error(start, "filters without a placeholder".into());
+        }
+
+        Ok(Node::Placeholder { names, filters })
+    }
+}
+
+fn push_text(nodes: &mut Vec<Node>, c: char) {
+    match nodes.last_mut() {
+        Some(Node::Text(text)) => text.push(c),
+        _ => nodes.push(Node::Text(c.to_string())),
+    }
+}
+
+/// Renders `nodes` into `out`, returns `false` if one of the placeholders was empty
+fn render_nodes(nodes: &[Node], values: &Values, out: &mut String) -> bool {
+    let mut complete = true;
+
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Separator => out.push(SEPARATOR),
+            Node::Placeholder { names, filters } => {
+                let value = names
+                    .iter()
+                    .filter_map(|name| values.get(name))
+                    .find(|value| !value.is_empty())
+                    .map(|value| {
+                        filters
+                            .iter()
+                            .fold(value, |value, filter| filter.apply(value))
+                    });
+
+                match value {
+                    Some(value) if !value.is_empty() => out.push_str(&value.to_string()),
+                    _ => complete = false,
+                }
+            }
+            Node::Section(nodes) => {
+                let mut section = String::new();
+
+                if render_nodes(nodes, values, &mut section) {
+                    out.push_str(&section);
+                }
+            }
+        }
+    }
+
+    complete
+}
+
+/// Collapses whitespace and removes separators that are doubled or at either end.
+fn tidy(input: &str) -> String {
+    let mut result = input.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    let separator = SEPARATOR.to_string();
+    let doubled = format!("{0}{0}", SEPARATOR);
+    let spaced = format!("{0} {0}", SEPARATOR);
+
+    while result.contains(&doubled) || result.contains(&spaced) {
+        result = result
+            .replace(&doubled, &separator)
+            .replace(&spaced, &separator);
+    }
+
+    result
+        .trim_matches(|c: char| c == SEPARATOR || c.is_whitespace())
+        .to_string()
+}
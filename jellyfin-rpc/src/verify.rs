@@ -0,0 +1,89 @@
+use crate::JfError;
+use std::fmt::Display;
+use url::Url;
+
+/// Result of `Client::verify()`, one report for every configured server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+    pub servers: Vec<ServerReport>,
+}
+
+impl VerifyReport {
+    /// Whether any server has a problem that won't go away without changing the config
+    pub fn is_fatal(&self) -> bool {
+        self.servers
+            .iter()
+            .any(|server| server.problems.iter().any(Problem::is_fatal))
+    }
+}
+
+/// What a single server reported about itself and the configured users.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerReport {
+    pub url: Url,
+    /// Name set in the server's dashboard, `None` if `/System/Info` couldn't be read
+    pub server_name: Option<String>,
+    /// e.g. `"Jellyfin Server"` or `"Emby Server"`
+    pub product_name: Option<String>,
+    pub version: Option<String>,
+    pub problems: Vec<Problem>,
+}
+
+/// Something that keeps sessions on a server from ever being found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The server couldn't be reached or answered with a server error, it might still be starting up
+    Unreachable(String),
+    /// The api key or access token was rejected
+    CredentialsRejected,
+    /// The server answered with something other than what Jellyfin would
+    UnexpectedResponse(String),
+    /// None of the users is called this
+    UnknownUsername(String),
+    /// None of the users has this id
+    UnknownUserId(String),
+    /// The user list couldn't be read so the users couldn't be checked, e.g. without admin rights
+    UsersNotListed(String),
+}
+
+impl Problem {
+    /// Unreachable servers and unreadable user lists can still work out later, everything else needs a config change
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, Problem::Unreachable(_) | Problem::UsersNotListed(_))
+    }
+}
+
+impl From<JfError> for Problem {
+    fn from(err: JfError) -> Self {
+        match err {
+            JfError::AuthenticationRejected => Problem::CredentialsRejected,
+            JfError::Jellyfin(err) if !err.is_decode() => Problem::Unreachable(err.to_string()),
+            // A proxy answers 502 or 503 while the server behind it is down or starting
+            err @ JfError::JellyfinStatus(status) if status.is_server_error() => {
+                Problem::Unreachable(err.to_string())
+            }
+            err => Problem::UnexpectedResponse(err.to_string()),
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Unreachable(err) => write!(f, "server is unreachable: {}", err),
+            Problem::CredentialsRejected => write!(f, "the api key or access token was rejected"),
+            Problem::UnexpectedResponse(err) => {
+                write!(f, "server doesn't look like Jellyfin: {}", err)
+            }
+            Problem::UnknownUsername(username) => write!(f, "no user is called {}", username),
+            Problem::UnknownUserId(id) => write!(f, "no user has the id {}", id),
+            Problem::UsersNotListed(err) => {
+                write!(
+                    f,
+                    "users could not be checked, the user list was refused: {}",
+                    err
+                )
+            }
+        }
+    }
+}